@@ -0,0 +1,317 @@
+use rltk::Point;
+use specs::prelude::*;
+
+use super::{CombatStats, Door, EntryTrigger, game_log::{GameLog, LogCategory}, Hidden, Item, Map, Monster, Name, pathfinding::{FlowFields, Goal},
+            player::{get_item, search, skip_turn, try_move_player}, Position, RunState, terrain::terrain, TileType, Viewshed};
+
+/// The most times a numeric prefix can repeat a command.
+const MAX_REPEAT: i32 = 999;
+
+/// Something the player has asked to keep doing over several turns.
+#[derive(PartialEq, Copy, Clone)]
+pub enum AutoAction {
+    Explore,
+    Travel { x: i32, y: i32 },
+    TravelToStairs,
+    Run { dx: i32, dy: i32, openness: Option<usize> },
+    Repeat { command: RepeatCommand, remaining: i32 }
+}
+
+/// Commands a numeric prefix can repeat.
+#[derive(PartialEq, Copy, Clone)]
+pub enum RepeatCommand {
+    Rest,
+    Search,
+    Step { dx: i32, dy: i32 }
+}
+
+/// The action in progress, how healthy the player was when the last step was taken,
+/// and any repeat count typed so far.
+#[derive(Default)]
+pub struct AutoPilot {
+    pub action: Option<AutoAction>,
+    pub count: i32,
+    last_hp: i32
+}
+
+impl AutoPilot {
+    pub fn new() -> AutoPilot {
+        AutoPilot { action: None, count: 0, last_hp: 0 }
+    }
+
+    /// Adds another digit to the repeat count.
+    pub fn push_digit(&mut self, digit: i32) {
+        self.count = i32::min(self.count * 10 + digit, MAX_REPEAT);
+    }
+
+    /// Uses up the repeat count, returning it if one was typed.
+    pub fn take_count(&mut self) -> Option<i32> {
+        let count = self.count;
+        self.count = 0;
+        if count > 0 { Some(count) } else { None }
+    }
+}
+
+/// Starts walking on the player's behalf, unless there is something nearby to worry about.
+pub fn start(ecs: &mut World, action: AutoAction) -> RunState {
+    if let Some(name) = visible_monster(ecs) {
+        ecs.fetch_mut::<GameLog>().push(LogCategory::General, format!("Not with a {} nearby!", name));
+        return RunState::AwaitingInput;
+    }
+
+    let hp = player_hp(ecs);
+    let mut autopilot = ecs.fetch_mut::<AutoPilot>();
+    autopilot.action = Some(action);
+    autopilot.last_hp = hp;
+    RunState::AwaitingInput
+}
+
+pub fn stop(ecs: &mut World) {
+    ecs.fetch_mut::<AutoPilot>().action = None;
+}
+
+/// Starts a trip to the nearest set of stairs the player knows about.
+pub fn travel_to_stairs(ecs: &mut World) -> RunState {
+    let found_stairs = {
+        let map = ecs.fetch::<Map>();
+        (0..map.tiles.len()).any(|idx| map.tiles[idx] == TileType::DownStairs && map.revealed_tiles[idx])
+    };
+
+    if !found_stairs {
+        ecs.fetch_mut::<GameLog>().push(LogCategory::General, "You haven't found the way down yet.");
+        return RunState::AwaitingInput;
+    }
+    start(ecs, AutoAction::TravelToStairs)
+}
+
+/// Takes the next step of whatever the player is doing, stopping as soon as a monster
+/// shows up, the player gets hurt or there is nowhere left to go.
+pub fn continue_action(ecs: &mut World) -> RunState {
+    let action = match ecs.fetch::<AutoPilot>().action {
+        None => return RunState::AwaitingInput,
+        Some(action) => action
+    };
+
+    if let Some(name) = visible_monster(ecs) {
+        stop(ecs);
+        ecs.fetch_mut::<GameLog>().push(LogCategory::Danger, format!("You spot a {}.", name));
+        return RunState::AwaitingInput;
+    }
+
+    let hp = player_hp(ecs);
+    if hp < ecs.fetch::<AutoPilot>().last_hp {
+        stop(ecs);
+        ecs.fetch_mut::<GameLog>().push(LogCategory::Danger, "You stop to tend to your wounds.");
+        return RunState::AwaitingInput;
+    }
+    ecs.fetch_mut::<AutoPilot>().last_hp = hp;
+
+    let player_pos = *ecs.fetch::<Point>();
+    let step = match action {
+        AutoAction::Repeat{ command, remaining } => {
+            ecs.fetch_mut::<AutoPilot>().action = if remaining > 1 {
+                Some(AutoAction::Repeat{ command, remaining: remaining - 1 })
+            } else {
+                None
+            };
+            match command {
+                RepeatCommand::Rest => return skip_turn(ecs),
+                RepeatCommand::Search => return search(ecs),
+                RepeatCommand::Step{ dx, dy } => {
+                    let map = ecs.fetch::<Map>();
+                    let (x, y) = (player_pos.x + dx, player_pos.y + dy);
+                    if map.blocked[map.xy_idx(x, y)] { None } else { Some((x, y)) }
+                }
+            }
+        }
+        AutoAction::Run{ dx, dy, openness } => {
+            let here = openness_at(ecs, player_pos);
+            let exits = corridor_exits(ecs, player_pos, dx, dy);
+            let opened_up = exits.is_none() && openness.filter(|before| *before != here).is_some();
+            let dead_end = exits.as_ref().filter(|exits| exits.len() != 1).is_some();
+            if opened_up || dead_end || (openness.is_some() && something_nearby(ecs, player_pos)) {
+                stop(ecs);
+                return RunState::AwaitingInput;
+            }
+
+            // Corridors are followed round their bends
+            let (dx, dy) = exits.map(|exits| exits[0]).unwrap_or((dx, dy));
+            ecs.fetch_mut::<AutoPilot>().action = Some(AutoAction::Run{ dx, dy, openness: Some(here) });
+
+            // Only keep running over more of the same ground
+            let map = ecs.fetch::<Map>();
+            let (x, y) = (player_pos.x + dx, player_pos.y + dy);
+            let (here_idx, next_idx) = (map.xy_idx(player_pos.x, player_pos.y), map.xy_idx(x, y));
+            if map.blocked[next_idx] || map.tiles[next_idx] != map.tiles[here_idx] { None } else { Some((x, y)) }
+        }
+        AutoAction::Explore => {
+            if item_underfoot(ecs, player_pos) {
+                get_item(ecs);
+                return RunState::PlayerTurn;
+            }
+            explore_step(ecs, player_pos)
+        }
+        AutoAction::Travel{ x, y } => {
+            if player_pos.x == x && player_pos.y == y {
+                stop(ecs);
+                return RunState::AwaitingInput;
+            }
+            let map = ecs.fetch::<Map>();
+            ecs.fetch_mut::<FlowFields>().next_step(&map, Goal::Travel{ x, y }, player_pos.x, player_pos.y)
+        }
+        AutoAction::TravelToStairs => {
+            let on_stairs = {
+                let map = ecs.fetch::<Map>();
+                map.tiles[map.xy_idx(player_pos.x, player_pos.y)] == TileType::DownStairs
+            };
+            if on_stairs {
+                stop(ecs);
+                return RunState::AwaitingInput;
+            }
+            let map = ecs.fetch::<Map>();
+            ecs.fetch_mut::<FlowFields>().next_step(&map, Goal::DownStairs, player_pos.x, player_pos.y)
+        }
+    };
+
+    match step {
+        None => {
+            stop(ecs);
+            let message = match action {
+                AutoAction::Explore => Some("There is nowhere left to explore."),
+                AutoAction::Travel{ .. } | AutoAction::TravelToStairs => Some("You can't find a way there."),
+                _ => None
+            };
+            if let Some(message) = message {
+                ecs.fetch_mut::<GameLog>().push(LogCategory::General, message);
+            }
+            RunState::AwaitingInput
+        }
+        Some((x, y)) => {
+            if let Some(trap) = known_trap(ecs, x, y) {
+                stop(ecs);
+                ecs.fetch_mut::<GameLog>().push(LogCategory::Danger, format!("You stop short of the {}.", trap));
+                return RunState::AwaitingInput;
+            }
+            try_move_player(x - player_pos.x, y - player_pos.y, ecs)
+        }
+    }
+}
+
+/// Heads for the nearest item in sight, or failing that the nearest unexplored tile.
+fn explore_step(ecs: &World, player_pos: Point) -> Option<(i32, i32)> {
+    let map = ecs.fetch::<Map>();
+    let mut flow_fields = ecs.fetch_mut::<FlowFields>();
+    let entities = ecs.entities();
+    let items = ecs.read_storage::<Item>();
+    let positions = ecs.read_storage::<Position>();
+
+    let mut visible_items: Vec<Point> = (&entities, &items, &positions).join()
+        .filter(|(_, _, pos)| map.visible_tiles[map.xy_idx(pos.x, pos.y)])
+        .map(|(_, _, pos)| Point::new(pos.x, pos.y))
+        .collect();
+    visible_items.sort_by_key(|item| rltk::DistanceAlg::Pythagoras.distance2d(*item, player_pos) as i32);
+
+    for item in visible_items.iter() {
+        let step = flow_fields.next_step(&map, Goal::Travel{ x: item.x, y: item.y }, player_pos.x, player_pos.y);
+        if step.is_some() { return step; }
+    }
+    flow_fields.next_step(&map, Goal::Unexplored, player_pos.x, player_pos.y)
+}
+
+/// How many of the tiles around a spot could be walked on. A change means the player has
+/// come to a side passage, a doorway or the edge of a room.
+fn openness_at(ecs: &World, pos: Point) -> usize {
+    let map = ecs.fetch::<Map>();
+    let mut open = 0;
+    for dy in -1 ..= 1 {
+        for dx in -1 ..= 1 {
+            if dx == 0 && dy == 0 { continue; }
+            let (x, y) = (pos.x + dx, pos.y + dy);
+            if x < 0 || x >= map.width || y < 0 || y >= map.height { continue; }
+            if terrain(map.tiles[map.xy_idx(x, y)]).walkable { open += 1; }
+        }
+    }
+    open
+}
+
+/// The ways on from a corridor tile, not counting the way back, when running straight along
+/// one. None if the player is in the open or running diagonally.
+fn corridor_exits(ecs: &World, pos: Point, dx: i32, dy: i32) -> Option<Vec<(i32, i32)>> {
+    if dx != 0 && dy != 0 { return None; }
+    let map = ecs.fetch::<Map>();
+    let walkable = |x: i32, y: i32| x >= 0 && x < map.width && y >= 0 && y < map.height && terrain(map.tiles[map.xy_idx(x, y)]).walkable;
+
+    let cardinals = [(0, -1), (0, 1), (-1, 0), (1, 0)];
+    let open: Vec<(i32, i32)> = cardinals.iter().copied().filter(|(cx, cy)| walkable(pos.x + cx, pos.y + cy)).collect();
+    if open.len() > 2 { return None; }
+
+    // Any open corner next to two open sides means a room, not a corridor
+    let in_room = [(-1, -1), (1, -1), (-1, 1), (1, 1)].iter()
+        .any(|(cx, cy)| walkable(pos.x + cx, pos.y + cy) && walkable(pos.x + cx, pos.y) && walkable(pos.x, pos.y + cy));
+    if in_room { return None; }
+
+    Some(open.into_iter().filter(|exit| *exit != (-dx, -dy)).collect())
+}
+
+/// True if there is an item, a door, a known trap or a way down within reach.
+fn something_nearby(ecs: &World, pos: Point) -> bool {
+    let map = ecs.fetch::<Map>();
+    let items = ecs.read_storage::<Item>();
+    let doors = ecs.read_storage::<Door>();
+    let triggers = ecs.read_storage::<EntryTrigger>();
+    let hidden = ecs.read_storage::<Hidden>();
+    for dy in -1 ..= 1 {
+        for dx in -1 ..= 1 {
+            let (x, y) = (pos.x + dx, pos.y + dy);
+            if x < 0 || x >= map.width || y < 0 || y >= map.height { continue; }
+            let idx = map.xy_idx(x, y);
+            if map.tiles[idx] == TileType::DownStairs { return true; }
+            let interesting = map.tile_content[idx].iter().any(|entity| {
+                hidden.get(*entity).is_none() && (items.get(*entity).is_some() || doors.get(*entity).is_some() || triggers.get(*entity).is_some())
+            });
+            if interesting { return true; }
+        }
+    }
+    false
+}
+
+fn item_underfoot(ecs: &World, player_pos: Point) -> bool {
+    let items = ecs.read_storage::<Item>();
+    let positions = ecs.read_storage::<Position>();
+    (&items, &positions).join().any(|(_, pos)| pos.x == player_pos.x && pos.y == player_pos.y)
+}
+
+/// The name of a trap the player knows is waiting on a tile.
+fn known_trap(ecs: &World, x: i32, y: i32) -> Option<String> {
+    let map = ecs.fetch::<Map>();
+    let triggers = ecs.read_storage::<EntryTrigger>();
+    let hidden = ecs.read_storage::<Hidden>();
+    let names = ecs.read_storage::<Name>();
+    map.tile_content[map.xy_idx(x, y)].iter()
+        .find(|entity| triggers.get(**entity).is_some() && hidden.get(**entity).is_none())
+        .map(|entity| names.get(*entity).map(|name| name.name.clone()).unwrap_or_else(|| "trap".to_string()))
+}
+
+fn visible_monster(ecs: &World) -> Option<String> {
+    let map = ecs.fetch::<Map>();
+    let player_entity = ecs.fetch::<Entity>();
+    let viewsheds = ecs.read_storage::<Viewshed>();
+    let monsters = ecs.read_storage::<Monster>();
+    let names = ecs.read_storage::<Name>();
+
+    let viewshed = viewsheds.get(*player_entity)?;
+    for tile in viewshed.visible_tiles.iter() {
+        let idx = map.xy_idx(tile.x, tile.y);
+        for entity in map.tile_content[idx].iter() {
+            if monsters.get(*entity).is_some() {
+                return Some(names.get(*entity).map(|name| name.name.clone()).unwrap_or_else(|| "monster".to_string()));
+            }
+        }
+    }
+    None
+}
+
+fn player_hp(ecs: &World) -> i32 {
+    let player_entity = ecs.fetch::<Entity>();
+    ecs.read_storage::<CombatStats>().get(*player_entity).map(|stats| stats.hp).unwrap_or(0)
+}
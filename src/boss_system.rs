@@ -0,0 +1,44 @@
+use specs::prelude::*;
+
+use super::{Boss, BossAbility, CombatStats, game_log::{GameLog, LogCategory, LogEntry}, Name, RunState};
+
+pub struct BossSystem {}
+
+impl<'a> System<'a> for BossSystem {
+    type SystemData = ( ReadExpect<'a, RunState>,
+                        WriteExpect<'a, GameLog>,
+                        WriteStorage<'a, Boss>,
+                        WriteStorage<'a, CombatStats>,
+                        ReadStorage<'a, Name>
+                      );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (runstate, mut log, mut bosses, mut combat_stats, names) = data;
+
+        if *runstate != RunState::MonsterTurn { return; }
+
+        for (boss, stats, name) in (&mut bosses, &mut combat_stats, &names).join() {
+            if stats.hp <= 0 { continue; }
+
+            match boss.ability {
+                BossAbility::Enrage => {
+                    // Enrages once, the first time it drops below half health
+                    if !boss.ability_active && stats.hp < stats.max_hp / 2 {
+                        boss.ability_active = true;
+                        stats.power += 4;
+                        log.add(LogEntry::new(LogCategory::Danger).text("The ").name(&name.name).text(" flies into a furious rage!"));
+                    }
+                }
+                BossAbility::Regenerate => {
+                    if stats.hp < stats.max_hp {
+                        stats.hp = i32::min(stats.max_hp, stats.hp + 1);
+                        if !boss.ability_active {
+                            boss.ability_active = true;
+                            log.add(LogEntry::new(LogCategory::Danger).text("The ").name(&name.name).text("'s wounds begin to close!"));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
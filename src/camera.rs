@@ -0,0 +1,145 @@
+use rltk::{Point, RGB, Rltk};
+use specs::prelude::*;
+
+use super::colors::*;
+use super::glyph_index::AETHER_GLYPH;
+use super::{Hidden, LastSeen, layout::Hud, map::wall_glyph, Map, Position, Renderable, run_stats::RunStats, TileType, terrain::terrain};
+
+/// How many turns a monster's "last seen here" marker takes to fade away.
+const SIGHTING_TURNS: i32 = 20;
+
+/// The part of the map on screen. It follows the player, but stops at the edges of the
+/// map so no more of the screen is wasted than has to be.
+#[derive(PartialEq, Copy, Clone)]
+pub struct Viewport {
+    pub min_x: i32,
+    pub min_y: i32,
+    pub width: i32,
+    pub height: i32
+}
+
+impl Viewport {
+    pub fn new(ecs: &World, ctx: &Rltk) -> Viewport {
+        let map = ecs.fetch::<Map>();
+        let player_pos = ecs.fetch::<Point>();
+        let area = Hud::new(ctx).map;
+        let (width, height) = (area.width, area.height);
+
+        let min_x = follow(player_pos.x, width, map.width);
+        let min_y = follow(player_pos.y, height, map.height);
+        Viewport { min_x, min_y, width, height }
+    }
+
+    /// Where a map tile is drawn, if it is on screen at all.
+    pub fn to_screen(&self, x: i32, y: i32) -> Option<(i32, i32)> {
+        let (screen_x, screen_y) = (x - self.min_x, y - self.min_y);
+        if screen_x < 0 || screen_x >= self.width || screen_y < 0 || screen_y >= self.height { return None; }
+        Some((screen_x, screen_y))
+    }
+
+    /// The nearest map tile to (x, y) that is on screen, so a cursor can't wander out of view.
+    pub fn clamp(&self, map: &Map, x: i32, y: i32) -> (i32, i32) {
+        let max_x = i32::min(map.width, self.min_x + self.width) - 1;
+        let max_y = i32::min(map.height, self.min_y + self.height) - 1;
+        (i32::max(self.min_x, i32::min(max_x, x)), i32::max(self.min_y, i32::min(max_y, y)))
+    }
+
+    /// The map tile under a spot on the screen, if there is one.
+    pub fn to_world(&self, map: &Map, screen_x: i32, screen_y: i32) -> Option<(i32, i32)> {
+        if screen_x < 0 || screen_x >= self.width || screen_y < 0 || screen_y >= self.height { return None; }
+        let (x, y) = (screen_x + self.min_x, screen_y + self.min_y);
+        if x < 0 || x >= map.width || y < 0 || y >= map.height { return None; }
+        Some((x, y))
+    }
+}
+
+/// The first map column (or row) to show so `pos` sits in the middle of the view.
+fn follow(pos: i32, view: i32, map: i32) -> i32 {
+    if map <= view { return 0; }
+    i32::max(0, i32::min(map - view, pos - view / 2))
+}
+
+/// Draws the map and everything visible on it.
+pub fn render_camera(ecs: &World, ctx: &mut Rltk) {
+    let viewport = Viewport::new(ecs, ctx);
+    draw_map(ecs, ctx, &viewport);
+
+    let map = ecs.fetch::<Map>();
+    let positions = ecs.read_storage::<Position>();
+    let renderables = ecs.read_storage::<Renderable>();
+    let hidden = ecs.read_storage::<Hidden>();
+    let last_seen = ecs.read_storage::<LastSeen>();
+    let turn = ecs.fetch::<RunStats>().turns;
+
+    // Things remembered on tiles out of sight, dimmed like the terrain under them
+    for (idx, memory) in map.remembered.iter() {
+        if map.visible_tiles[*idx] { continue; }
+        let (x, y) = (*idx as i32 % map.width, *idx as i32 / map.width);
+        if let Some((screen_x, screen_y)) = viewport.to_screen(x, y) {
+            ctx.set(screen_x, screen_y, return_rgb(OUT_OF_VIEW), return_rgb(DEFAULT_BG), memory.glyph);
+        }
+    }
+
+    // Monsters fade out from where they were last seen
+    for (render, seen) in (&renderables, &last_seen).join() {
+        let age = turn - seen.turn;
+        if age >= SIGHTING_TURNS || map.visible_tiles[map.xy_idx(seen.x, seen.y)] { continue; }
+        if let Some((screen_x, screen_y)) = viewport.to_screen(seen.x, seen.y) {
+            let fg = return_rgb(LAST_SEEN_FG).lerp(return_rgb(DEFAULT_BG), age as f32 / SIGHTING_TURNS as f32);
+            ctx.set(screen_x, screen_y, fg, return_rgb(DEFAULT_BG), render.glyph);
+        }
+    }
+
+    let mut data = (&positions, &renderables, !&hidden).join().collect::<Vec<_>>();
+    data.sort_by(|&a, &b| b.1.render_order.cmp(&a.1.render_order) );
+
+    for (pos, render, _hidden) in data.iter() {
+        let idx = map.xy_idx(pos.x, pos.y);
+        if !map.visible_tiles[idx] { continue; }
+        if let Some((screen_x, screen_y)) = viewport.to_screen(pos.x, pos.y) {
+            ctx.set(screen_x, screen_y, render.fg, render.bg, render.glyph);
+        }
+    }
+}
+
+fn draw_map(ecs: &World, ctx: &mut Rltk, viewport: &Viewport) {
+    let map = ecs.fetch::<Map>();
+
+    for screen_y in 0 .. viewport.height {
+        for screen_x in 0 .. viewport.width {
+            let (x, y) = match viewport.to_world(&map, screen_x, screen_y) {
+                None => continue,
+                Some(tile) => tile
+            };
+            let idx = map.xy_idx(x, y);
+            let tile = map.tiles[idx];
+
+            // Render a tile depending upon the tile type
+            if map.revealed_tiles[idx] {
+                let tile_terrain = terrain(tile);
+                let glyph = match tile {
+                    TileType::Wall => wall_glyph(&map, x, y),
+                    _ => rltk::to_cp437(tile_terrain.glyph)
+                };
+                let mut fg: RGB = return_rgb(tile_terrain.fg);
+                let mut bg: RGB = return_rgb(tile_terrain.bg);
+                if map.wall_damage.contains_key(&idx) { fg = return_rgb(CRACKED_WALL_COLOR); }
+                if map.bloodstains.contains(&idx) { bg = return_rgb(BLOOD_BG);}
+                if map.visible_tiles[idx] {
+                    // Tint by the light falling on the tile, never quite down to black
+                    let (r, g, b) = map.light[idx];
+                    let light = RGB::from_f32(f32::min(1.0, 0.35 + r * 0.65), f32::min(1.0, 0.35 + g * 0.65), f32::min(1.0, 0.35 + b * 0.65));
+                    fg = fg * light;
+                    bg = bg * light;
+                }
+                if !map.visible_tiles[idx] {
+                    fg = return_rgb(OUT_OF_VIEW);
+                    bg = return_rgb(DEFAULT_BG);
+                }
+                ctx.set(screen_x, screen_y, fg, bg, glyph);
+            } else {
+                ctx.set(screen_x, screen_y, return_rgb(AETHER_FG), return_rgb(DEFAULT_BG), rltk::to_cp437(AETHER_GLYPH));
+            }
+        }
+    }
+}
@@ -23,6 +23,10 @@ pub const PLAYER_FG: (f32, f32, f32) = DB16_LIGHT8_F32;
 pub const ENEMY_FG: (f32, f32, f32) = DB16_DARK7_F32;
 pub const ORC_FG: (f32, f32, f32) = DB16_LIGHT3_F32;
 pub const GOBLIN_FG: (f32, f32, f32) = DB16_LIGHT4_F32;
+pub const WARLORD_FG: (f32, f32, f32) = DB16_LIGHT2_F32;
+pub const OVERLORD_FG: (f32, f32, f32) = DB16_DARK7_F32;
+pub const MERCHANT_FG: (f32, f32, f32) = DB16_LIGHT6_F32;
+pub const VILLAGER_FG: (f32, f32, f32) = DB16_LIGHT5_F32;
 
 // Items
 pub const HEALTH_POT_FG: (f32, f32, f32) = DB16_DARK7_F32;
@@ -31,21 +35,49 @@ pub const MMS_FG: (f32, f32, f32) = DB16_LIGHT6_F32;
 pub const FIREBALL_FG: (f32, f32, f32) = DB16_LIGHT2_F32;
 pub const RATION_FG: (f32, f32, f32) = DB16_DARK6_F32;
 pub const MAGICMAP_FG: (f32, f32, f32) = DB16_DARK2_F32;
+pub const GOLD_FG: (f32, f32, f32) = DB16_LIGHT7_F32;
+pub const KEY_FG: (f32, f32, f32) = DB16_LIGHT7_F32;
+pub const MCGUFFIN_FG: (f32, f32, f32) = DB16_LIGHT6_F32;
 
 // Equipment
 pub const DAGGER_FG: (f32, f32, f32) = DB16_LIGHT6_F32;
 pub const SHIELD_FG: (f32, f32, f32) = DB16_LIGHT6_F32;
 pub const TOWER_S_FG: (f32, f32, f32) = DB16_LIGHT7_F32;
 pub const SWORD_FG: (f32, f32, f32) = DB16_LIGHT7_F32;
+pub const PICKAXE_FG: (f32, f32, f32) = DB16_LIGHT3_F32;
 
 // Dungeon
 pub const WALL_COLOR: (f32, f32, f32) = DB16_LIGHT5_F32;
+pub const LANTERN_LIGHT: (f32, f32, f32) = (1.0, 0.9, 0.65);
+pub const TORCH_LIGHT: (f32, f32, f32) = (1.0, 0.65, 0.3);
+pub const FUNGUS_LIGHT: (f32, f32, f32) = (0.45, 0.9, 0.5);
+pub const FIRE_LIGHT: (f32, f32, f32) = (1.0, 0.5, 0.2);
+pub const TORCH_FG: (f32, f32, f32) = DB16_LIGHT2_F32;
+pub const FUNGUS_FG: (f32, f32, f32) = DB16_LIGHT4_F32;
+pub const CRACKED_WALL_COLOR: (f32, f32, f32) = DB16_DARK8_F32;
 pub const FLOOR_COLOR: (f32, f32, f32) = DB16_DARK5_F32;
 pub const STAIRS_FG: (f32, f32, f32) = DB16_LIGHT2_F32;
 pub const OUT_OF_VIEW: (f32, f32, f32) = DB16_DARK2_F32;
+pub const LAST_SEEN_FG: (f32, f32, f32) = DB16_DARK7_F32;
 pub const AETHER_FG: (f32,f32,f32) = DB16_DARK4_F32;
 pub const BLOOD_BG: (f32, f32, f32) = DB16_DARK7_F32;
+pub const WATER_FG: (f32, f32, f32) = DB16_LIGHT6_F32;
+pub const DEEP_WATER_FG: (f32, f32, f32) = DB16_LIGHT1_F32;
+pub const DEEP_WATER_BG: (f32, f32, f32) = DB16_DARK3_F32;
+pub const LAVA_FG: (f32, f32, f32) = DB16_LIGHT7_F32;
+pub const LAVA_BG: (f32, f32, f32) = DB16_DARK7_F32;
+pub const CHASM_FG: (f32, f32, f32) = DB16_DARK4_F32;
+pub const GRASS_FG: (f32, f32, f32) = DB16_LIGHT4_F32;
+pub const RUBBLE_FG: (f32, f32, f32) = DB16_LIGHT3_F32;
 pub const BEARTRAP_FG: (f32, f32, f32) = DB16_DARK7_F32;
+pub const DOOR_FG: (f32, f32, f32) = DB16_LIGHT5_F32;
+pub const LOCKED_DOOR_FG: (f32, f32, f32) = DB16_LIGHT7_F32;
+pub const TELEPORT_TRAP_FG: (f32, f32, f32) = DB16_LIGHT6_F32;
+pub const ALARM_TRAP_FG: (f32, f32, f32) = DB16_LIGHT7_F32;
+pub const PIT_TRAP_FG: (f32, f32, f32) = DB16_DARK5_F32;
+pub const POISON_TRAP_FG: (f32, f32, f32) = DB16_LIGHT4_F32;
+pub const GAS_TRAP_FG: (f32, f32, f32) = DB16_LIGHT1_F32;
+pub const POISON_FG: (f32, f32, f32) = DB16_LIGHT4_F32;
 
 // User Interface
 pub const HEALTH_BAR_FG: (f32, f32, f32) = DB16_DARK7_F32;
@@ -56,6 +88,22 @@ pub const ERROR_BG: (f32,f32,f32) = DB16_DARK7_F32;
 pub const TITLE_FG: (f32,f32,f32) = DB16_LIGHT7_F32;
 pub const SELECT_FG: (f32, f32, f32) = DB16_LIGHT8_F32;
 pub const NOTSELECT_FG: (f32, f32, f32) = DB16_LIGHT6_F32;
+pub const LOOK_BG: (f32, f32, f32) = DB16_LIGHT6_F32;
+pub const PATH_BG: (f32, f32, f32) = DB16_DARK5_F32;
+pub const BLAST_BG: (f32, f32, f32) = DB16_LIGHT2_F32;
+
+// UI - Overview map
+pub const OVERVIEW_FLOOR_FG: (f32, f32, f32) = DB16_DARK8_F32;
+pub const OVERVIEW_WALL_FG: (f32, f32, f32) = DB16_DARK4_F32;
+pub const OVERVIEW_ITEM_FG: (f32, f32, f32) = DB16_LIGHT7_F32;
+
+// UI - Message log
+pub const LOG_GENERAL_FG: (f32, f32, f32) = DB16_LIGHT8_F32;
+pub const LOG_COMBAT_FG: (f32, f32, f32) = DB16_LIGHT5_F32;
+pub const LOG_ITEM_FG: (f32, f32, f32) = DB16_LIGHT6_F32;
+pub const LOG_DANGER_FG: (f32, f32, f32) = DB16_DARK7_F32;
+pub const LOG_NAME_FG: (f32, f32, f32) = DB16_LIGHT7_F32;
+pub const LOG_TURN_FG: (f32, f32, f32) = DB16_DARK8_F32;
 
 // Particles
 pub const AOE_FG: (f32, f32, f32) = DB16_LIGHT2_F32;
@@ -68,6 +116,7 @@ pub const MENU_FG: (f32, f32, f32) = DB16_LIGHT8_F32;
 pub const INV_BG: (f32, f32, f32) = DB16_LIGHT6_F32;
 pub const DROP_BG: (f32, f32, f32) = DB16_DARK7_F32;
 pub const REMOVE_BG: (f32, f32, f32) = DB16_LIGHT2_F32;
+pub const SHOP_BG: (f32, f32, f32) = DB16_DARK3_F32;
 pub const HELP_FG: (f32, f32, f32) = DB16_LIGHT3_F32;
 pub const HELP_BG: (f32, f32, f32) = DB16_LIGHT1_F32;
 pub const CTRL_FG: (f32, f32, f32) = DB16_LIGHT7_F32;
@@ -81,5 +130,15 @@ pub const FED: (f32, f32, f32) = DB16_DARK6_F32;
 pub const HUNGRY: (f32, f32, f32) = DB16_LIGHT2_F32;
 pub const STARVING: (f32, f32, f32) = DB16_DARK7_F32;
 
+// UI - Stealth
+pub const SNEAK_FG: (f32, f32, f32) = DB16_LIGHT3_F32;
+
+// UI - Look mode threat levels
+pub const THREAT_LOW: (f32, f32, f32) = DB16_LIGHT4_F32;
+pub const THREAT_MODERATE: (f32, f32, f32) = DB16_LIGHT7_F32;
+pub const THREAT_HIGH: (f32, f32, f32) = DB16_LIGHT2_F32;
+pub const THREAT_DEADLY: (f32, f32, f32) = DB16_DARK7_F32;
+
 // UI - GameOver
 pub const GAME_OVER_FG: (f32, f32, f32) = DB16_DARK7_F32;
+pub const VICTORY_FG: (f32, f32, f32) = DB16_LIGHT7_F32;
@@ -46,6 +46,11 @@ pub struct Name {
     pub name: String
 }
 
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Description {
+    pub description: String
+}
+
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct BlocksTile {}
 
@@ -196,6 +201,125 @@ pub struct EntityMoved {}
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct SingleActivation {}
 
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct TeleportsVictim {}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Alarm {
+    pub radius: i32
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Pit {}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct InflictsPoison {
+    pub damage: i32,
+    pub turns: i32
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct InflictsConfusion {
+    pub turns: i32
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Poisoned {
+    pub damage: i32,
+    pub turns: i32
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Asleep {}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Slowed {}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Investigating {
+    pub x: i32,
+    pub y: i32
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Sneaking {}
+
+/// Where and when the player last saw a monster.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct LastSeen {
+    pub x: i32,
+    pub y: i32,
+    pub turn: i32
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct LightSource {
+    pub color: (f32, f32, f32),
+    pub range: i32
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct DiggingTool {
+    pub power: i32
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Door {
+    pub open: bool
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct BlocksVisibility {}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct LockedDoor {
+    pub key_id: i32
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Key {
+    pub key_id: i32
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct ItemValue {
+    pub value: i32
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Wallet {
+    pub gold: i32
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct GoldPile {
+    pub amount: i32
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Vendor {}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Npc {}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct McGuffin {}
+
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum BossAbility { Enrage, Regenerate }
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Boss {
+    pub ability: BossAbility,
+    pub ability_active: bool,
+    pub death_message: String
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Dialogue {
+    pub lines: Vec<String>
+}
+
 // Serialization helper code. We need to implement ConvertSaveload for each type that
 // contains an Entity.
 
@@ -204,5 +328,8 @@ pub struct SerializeMe;
 // Special component that exist to help serialize the game data
 #[derive(Component, Serialize, Deserialize, Clone)]
 pub struct SerializationHelper {
-    pub map: super::map::Map
+    pub map: super::map::Map,
+    pub run_stats: super::run_stats::RunStats,
+    pub log: super::game_log::GameLog,
+    pub settings: super::settings::GameSettings
 }
\ No newline at end of file
@@ -1,5 +1,5 @@
 use specs::prelude::*;
-use super::{CombatStats, game_log::GameLog, Map, Name, Player, Position, RunState, SufferDamage};
+use super::{Asleep, Boss, CombatStats, game_log::{GameLog, LogCategory, LogEntry}, Map, Name, Player, Position, RunState, run_stats::RunStats, spawner, SufferDamage, Wallet};
 
 pub struct DamageSystem {}
 
@@ -8,14 +8,17 @@ impl<'a> System<'a> for DamageSystem {
                         WriteStorage<'a, SufferDamage>,
                         ReadStorage<'a, Position>,
                         WriteExpect<'a, Map>,
-                        Entities<'a>
+                        Entities<'a>,
+                        WriteStorage<'a, Asleep>
                     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut stats, mut damage, positions, mut map, entities) = data;
+        let (mut stats, mut damage, positions, mut map, entities, mut asleep) = data;
 
         for (entity, mut stats, damage) in (&entities, &mut stats, &damage).join() {
             stats.hp -= damage.amount.iter().sum::<i32>();
+            asleep.remove(entity); // Nobody sleeps through being hurt
+
             let pos = positions.get(entity);
             if let Some(pos) = pos {
                 let idx = map.xy_idx(pos.x, pos.y);
@@ -29,12 +32,17 @@ impl<'a> System<'a> for DamageSystem {
 
 pub fn delete_the_dead(ecs: &mut World) {
     let mut dead: Vec<Entity> = Vec::new();
+    let mut gold_drops: Vec<(i32, i32, i32)> = Vec::new();
     {
         let combat_stats = ecs.read_storage::<CombatStats>();
         let players = ecs.read_storage::<Player>();
         let names = ecs.read_storage::<Name>();
+        let positions = ecs.read_storage::<Position>();
+        let wallets = ecs.read_storage::<Wallet>();
+        let bosses = ecs.read_storage::<Boss>();
         let entities = ecs.entities();
         let mut log = ecs.write_resource::<GameLog>();
+        let mut run_stats = ecs.write_resource::<RunStats>();
         for (entity, stats) in (&entities, &combat_stats).join() {
             if stats.hp < 1 {
                 let player = players.get(entity);
@@ -42,8 +50,20 @@ pub fn delete_the_dead(ecs: &mut World) {
                     None => {
                         let victim_name = names.get(entity);
                         if let Some(victim_name) = victim_name {
-                            log.entries.push(format!("{} is dead", &victim_name.name));
+                            log.add(LogEntry::new(LogCategory::Combat).name(&victim_name.name).text(" is dead"));
+                            if let Some(boss) = bosses.get(entity) {
+                                log.push(LogCategory::Combat, &boss.death_message);
+                                run_stats.bosses_killed.push(victim_name.name.clone());
+                            }
+                        }
+
+                        // Drop whatever gold they were carrying
+                        if let (Some(wallet), Some(pos)) = (wallets.get(entity), positions.get(entity)) {
+                            if wallet.gold > 0 {
+                                gold_drops.push((pos.x, pos.y, wallet.gold));
+                            }
                         }
+                        run_stats.kills += 1;
                         dead.push(entity)
                     }
                     Some(_) => {
@@ -58,4 +78,8 @@ pub fn delete_the_dead(ecs: &mut World) {
     for victim in dead {
         ecs.delete_entity(victim).expect("Unable to delete");
     }
+
+    for (x, y, amount) in gold_drops {
+        spawner::gold_pile(ecs, x, y, amount);
+    }
 }
\ No newline at end of file
@@ -1,3 +1,115 @@
+use serde::{Serialize, Deserialize};
+
+use super::colors::*;
+
+/// The most entries the log keeps; the oldest are forgotten first.
+const MAX_LOG_ENTRIES: usize = 500;
+
+/// A message repeated within this many turns of the last one is counted rather than logged again.
+const MERGE_TURNS: i32 = 3;
+
+/// What a message is about, so the history can be filtered.
+#[derive(PartialEq, Eq, Copy, Clone, Serialize, Deserialize, Debug)]
+pub enum LogCategory { General, Combat, Item, Danger }
+
+impl LogCategory {
+    pub const ALL: [LogCategory; 4] = [LogCategory::General, LogCategory::Combat, LogCategory::Item, LogCategory::Danger];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LogCategory::General => "General",
+            LogCategory::Combat => "Combat",
+            LogCategory::Item => "Items",
+            LogCategory::Danger => "Danger"
+        }
+    }
+
+    /// The colour of plain text in this kind of message.
+    pub fn color(&self) -> (f32, f32, f32) {
+        match self {
+            LogCategory::General => LOG_GENERAL_FG,
+            LogCategory::Combat => LOG_COMBAT_FG,
+            LogCategory::Item => LOG_ITEM_FG,
+            LogCategory::Danger => LOG_DANGER_FG
+        }
+    }
+}
+
+/// A run of text in one colour.
+#[derive(PartialEq, Clone, Serialize, Deserialize)]
+pub struct LogFragment {
+    pub text: String,
+    pub color: (f32, f32, f32)
+}
+
+/// One message, built up from fragments so names can stand out.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub category: LogCategory,
+    pub turn: i32,
+    pub fragments: Vec<LogFragment>,
+    pub count: i32
+}
+
+impl LogEntry {
+    pub fn new(category: LogCategory) -> LogEntry {
+        LogEntry { category, turn: 0, fragments: Vec::new(), count: 1 }
+    }
+
+    /// Adds text in the category's colour.
+    pub fn text<S: ToString>(self, text: S) -> LogEntry {
+        let color = self.category.color();
+        self.color(text, color)
+    }
+
+    /// Adds the name of someone or something, picked out from the rest of the message.
+    pub fn name<S: ToString>(self, name: S) -> LogEntry {
+        self.color(name, LOG_NAME_FG)
+    }
+
+    pub fn color<S: ToString>(mut self, text: S, color: (f32, f32, f32)) -> LogEntry {
+        self.fragments.push(LogFragment{ text: text.to_string(), color });
+        self
+    }
+
+    /// The whole message without its colours.
+    pub fn plain_text(&self) -> String {
+        self.fragments.iter().map(|fragment| fragment.text.as_str()).collect()
+    }
+}
+
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct GameLog {
-    pub entries: Vec<String>
-}
\ No newline at end of file
+    pub entries: Vec<LogEntry>,
+    pub turn: i32
+}
+
+impl GameLog {
+    pub fn new() -> GameLog {
+        GameLog { entries: Vec::new(), turn: 0 }
+    }
+
+    /// Logs a message in its category's colour.
+    pub fn push<S: ToString>(&mut self, category: LogCategory, text: S) {
+        self.add(LogEntry::new(category).text(text));
+    }
+
+    /// Logs a message built from fragments. Saying the same thing again soon after
+    /// just counts the repeat.
+    pub fn add(&mut self, mut entry: LogEntry) {
+        entry.turn = self.turn;
+        if let Some(last) = self.entries.last_mut() {
+            if last.category == entry.category && last.fragments == entry.fragments && entry.turn - last.turn <= MERGE_TURNS {
+                last.count += 1;
+                last.turn = entry.turn;
+                return;
+            }
+        }
+
+        self.entries.push(entry);
+        if self.entries.len() > MAX_LOG_ENTRIES {
+            let excess = self.entries.len() - MAX_LOG_ENTRIES;
+            self.entries.drain(0..excess);
+        }
+    }
+}
@@ -4,24 +4,47 @@ pub const PLAYER_GLYPH: char = '@';
 // Mob's, NPC's, etc. glyphs
 pub const GOBLIN_GLYPH: char = 'g';
 pub const ORC_GLYPH: char = 'o';
+pub const WARLORD_GLYPH: char = 'G';
+pub const OVERLORD_GLYPH: char = 'O';
+pub const MERCHANT_GLYPH: char = '☺';
+pub const VILLAGER_GLYPH: char = '☻';
 
 // Items
 pub const SCROLL_GLYPH: char = '‼';
 pub const POTION_GLYPH: char = '¡';
 pub const RATIONS_GLYPH: char = '≡';
+pub const GOLD_GLYPH: char = '$';
+pub const MCGUFFIN_GLYPH: char = '♦';
+pub const KEY_GLYPH: char = '♀';
 
 // Equipment
 pub const DAGGER_GLYPH: char = '-';
 pub const SHIELD_GLYPH: char = '(';
 pub const SWORD_GLYPH: char = '/';
 pub const TOWER_S_GLYPH: char = '(';
+pub const PICKAXE_GLYPH: char = 'τ';
 
 // dungeon
 pub const WALL_GLYPH: char = '#';
 pub const FLOOR_GLYPH: char = '.';
 pub const STAIRS_GLYPH: char = '»';
 pub const AETHER_GLYPH: char = ' ';
-pub const BEARTRAP_GLYPH: char = '^';
+pub const TRAP_GLYPH: char = '^';
+pub const SHALLOW_WATER_GLYPH: char = '~';
+pub const DEEP_WATER_GLYPH: char = '≈';
+pub const LAVA_GLYPH: char = '≈';
+pub const CHASM_GLYPH: char = '∙';
+pub const TALL_GRASS_GLYPH: char = '"';
+pub const RUBBLE_GLYPH: char = ',';
+pub const TORCH_GLYPH: char = '*';
+pub const FUNGUS_GLYPH: char = '♣';
+pub const DOOR_CLOSED_GLYPH: char = '+';
+pub const DOOR_OPEN_GLYPH: char = '\'';
+
+// Overview map
+pub const OVERVIEW_FLOOR_GLYPH: char = '∙';
+pub const OVERVIEW_WALL_GLYPH: char = '█';
+pub const OVERVIEW_ITEM_GLYPH: char = '•';
 
 // Particles
 pub const POW_GLYPH: char = '☼';
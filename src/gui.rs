@@ -1,10 +1,9 @@
 use rltk::{ Point, RGB, Rltk, VirtualKeyCode};
 use specs::prelude::*;
 
-use rltk::Rect;
-
 use super::colors::*;
-use super::{ CombatStats, Equipped, game_log::GameLog, Hidden, HungerClock, HungerState, InBackpack, Map, Name, Player, Position, rex_assets::RexAssets, RunState, State, Viewshed };
+use super::{ AreaOfEffect, Asleep, auto_action::AutoPilot, camera::Viewport, CombatStats, Confusion, DefenseBonus, Description, Equipped, game_log::{GameLog, LogCategory, LogEntry}, Hidden, HungerClock, HungerState, InBackpack, input::{self, Input}, layout::{self, Anchor, Hud, Panel}, Map,
+             ItemValue, melee_combat_system::hunger_power_bonus, MeleePowerBonus, Monster, Name, overview, Player, Poisoned, Position, rex_assets::RexAssets, keymap::{Action, Context, KeyPress, Keymap, Preset}, run_stats::RunStats, RunState, Sneaking, State, targeting, terrain::terrain, Wallet };
 
 pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
     let fg: RGB = return_rgb(DEFAULT_FG);
@@ -12,59 +11,69 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
 
     let m_bg: RGB = return_rgb(MOUSE_BG);
 
-    let info_title = format!(" Player Info. ");
-    let msg_title = format!(" Message Log ");
-
-    let bg_rect = Rect::with_size(0, 40, 79, 49);
-
-    ctx.fill_region(bg_rect, rltk::to_cp437(' '), fg, bg);
-
-    ctx.draw_hollow_box(0, 40, 18, 9, fg, bg);
-    ctx.draw_hollow_box(18, 40, 79-18, 9, fg, bg);
+    let hud = Hud::new(ctx);
+    hud.status.draw_box(ctx, fg, bg);
+    hud.log.draw_box(ctx, fg, bg);
+    hud.minimap.draw_box(ctx, fg, bg);
 
-    ctx.set(18, 40, fg, bg, rltk::to_cp437('┬'));
-    ctx.set(18, 49, fg, bg, rltk::to_cp437('┴'));
+    // Join up the borders the panels share
+    for panel in [hud.log, hud.minimap].iter() {
+        ctx.set(panel.x, panel.y, fg, bg, rltk::to_cp437('┬'));
+        ctx.set(panel.x, panel.bottom(), fg, bg, rltk::to_cp437('┴'));
+    }
 
-    ctx.set(1, 40, fg, bg, rltk::to_cp437('┤'));
-    ctx.print_color(2, 40, fg, bg, info_title);
-    ctx.set(16, 40, fg, bg, rltk::to_cp437('├'));
+    hud.status.title(ctx, fg, bg, " Player Info. ");
+    hud.log.title(ctx, fg, bg, " Message Log ");
+    hud.minimap.title(ctx, fg, bg, " Map ");
+    let minimap = hud.minimap.inner();
+    overview::draw_overview(ecs, ctx, minimap.x, minimap.y, minimap.width, minimap.height);
 
-    ctx.set(19, 40, fg, bg, rltk::to_cp437('┤'));
-    ctx.print_color(20, 40, fg, bg, msg_title);
-    ctx.set(33, 40, fg, bg, rltk::to_cp437('├'));
-    
     let combat_stats = ecs.read_storage::<CombatStats>();
     let players = ecs.read_storage::<Player>();
     let hunger = ecs.read_storage::<HungerClock>();
+    let wallets = ecs.read_storage::<Wallet>();
+    let sneaking = ecs.read_storage::<Sneaking>();
+
+    // Status lines start one row and column in from the panel's border
+    let (x, y) = (hud.status.x + 2, hud.status.y + 2);
+
+    for (_player, wallet) in (&players, &wallets).join() {
+        let gold = format!("Gold: {}", wallet.gold);
+        ctx.print_color(x, y + 4, return_rgb(GOLD_FG), bg, &gold);
+    }
+
+    for (_player, _sneaking) in (&players, &sneaking).join() {
+        ctx.print_color(x, y + 3, return_rgb(SNEAK_FG), bg, "Sneaking");
+    }
+
+    let count = ecs.fetch::<AutoPilot>().count;
+    if count > 0 {
+        ctx.print_color(x, y + 5, fg, bg, &format!("Repeat: {}", count));
+    }
 
     for (_player, stats, hc) in (&players, &combat_stats, &hunger).join() {
-        let health = format!("HP: {} / {} ", stats.hp, stats.max_hp);        
-        
-        ctx.print_color(2, 42, fg, bg, &health);
+        let health = format!("HP: {} / {} ", stats.hp, stats.max_hp);
+
+        ctx.print_color(x, y, fg, bg, &health);
 
         let bar_fg: RGB = return_rgb(HEALTH_BAR_FG);
         let bar_bg: RGB = return_rgb(DEFAULT_BG);
 
-        ctx.draw_bar_horizontal(2, 43, 15, stats.hp, stats.max_hp, bar_fg, bar_bg);
+        ctx.draw_bar_horizontal(x, y + 1, hud.status.width - 4, stats.hp, stats.max_hp, bar_fg, bar_bg);
 
-        match hc.state {
-            HungerState::WellFed => ctx.print_color(2, 44, return_rgb(WELLFED), return_rgb(DEFAULT_BG), "Well Fed"),
-            HungerState::Normal => ctx.print_color(2, 44, return_rgb(FED), return_rgb(DEFAULT_BG), "Fed"),
-            HungerState::Hungry => ctx.print_color(2, 44, return_rgb(HUNGRY), return_rgb(DEFAULT_BG), "Hungry"),
-            HungerState::Starving => ctx.print_color(2, 44, return_rgb(STARVING), return_rgb(DEFAULT_BG), "Starving"),
-        }
+        let (hunger_text, hunger_fg) = hunger_status(hc.state);
+        ctx.print_color(x, y + 2, hunger_fg, bg, hunger_text);
     }
 
     let map = ecs.fetch::<Map>();
-    let depth = format!(" Depth: {} ", map.depth);
-    ctx.print_color(1, 49, return_rgb(DEFAULT_BG), return_rgb(DEFAULT_FG), &depth);
+    let depth = if map.depth == 0 { " Town ".to_string() } else { format!(" Depth: {} ", map.depth) };
+    ctx.print_color(hud.status.x + 1, hud.status.bottom(), return_rgb(DEFAULT_BG), return_rgb(DEFAULT_FG), &depth);
 
+    // Newest messages first, as many as fit
     let log = ecs.fetch::<GameLog>();
-
-    let mut y = 41;
-    for s in log.entries.iter().rev() {
-        if y < 49 { ctx.print_color(20, y, fg, bg, s);}
-        y += 1;
+    let log_area = hud.log.inner();
+    for (entry, y) in log.entries.iter().rev().zip(log_area.y ..= log_area.bottom()) {
+        print_log_entry(ctx, log_area.x + 1, y, log_area.width - 2, entry, bg);
     }
 
     // Draw Mouse Cursor
@@ -73,6 +82,32 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
     draw_tooltips(ecs, ctx);
 }
 
+fn hunger_status(state: HungerState) -> (&'static str, RGB) {
+    match state {
+        HungerState::WellFed => ("Well Fed", return_rgb(WELLFED)),
+        HungerState::Normal => ("Fed", return_rgb(FED)),
+        HungerState::Hungry => ("Hungry", return_rgb(HUNGRY)),
+        HungerState::Starving => ("Starving", return_rgb(STARVING))
+    }
+}
+
+/// Prints a log entry a fragment at a time, cut off after `width` characters.
+fn print_log_entry(ctx: &mut Rltk, x: i32, y: i32, width: i32, entry: &LogEntry, bg: RGB) {
+    let mut text_x = x;
+    let repeats = format!(" x{}", entry.count);
+    let repeat_fragment = (repeats, entry.category.color());
+    let fragments = entry.fragments.iter()
+        .map(|fragment| (fragment.text.clone(), fragment.color))
+        .chain(std::iter::once(repeat_fragment).filter(|_| entry.count > 1));
+    for (text, color) in fragments {
+        let room = x + width - text_x;
+        if room <= 0 { return; }
+        let text: String = text.chars().take(room as usize).collect();
+        ctx.print_color(text_x, y, return_rgb(color), bg, &text);
+        text_x += text.chars().count() as i32;
+    }
+}
+
 fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
     let map = ecs.fetch::<Map>();
     let names = ecs.read_storage::<Name>();
@@ -80,11 +115,15 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
     let hidden = ecs.read_storage::<Hidden>();
 
     let mouse_pos = ctx.mouse_pos();
-    if mouse_pos.0 >= map.width || mouse_pos.1 >= map.height { return; }
+    let viewport = Viewport::new(ecs, ctx);
+    let (mouse_x, mouse_y) = match viewport.to_world(&map, mouse_pos.0, mouse_pos.1) {
+        None => return,
+        Some(tile) => tile
+    };
     let mut tooltip: Vec<String> = Vec::new();
     for (name, position, _hidden) in (&names, &positions, !&hidden).join() {
         let idx = map.xy_idx(position.x, position.y);
-        if position.x == mouse_pos.0 && position.y == mouse_pos.1 && map.visible_tiles[idx] {
+        if position.x == mouse_x && position.y == mouse_y && map.visible_tiles[idx] {
             tooltip.push(name.name.to_string());
         }
     }
@@ -99,7 +138,8 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
         }
         width += 3;
 
-        if mouse_pos.0 > 40 {
+        // Point back towards the middle of the map, so the tooltip stays on screen
+        if mouse_pos.0 > viewport.width / 2 {
             let arrow_pos = Point::new(mouse_pos.0 - 2, mouse_pos.1);
             let left_x = mouse_pos.0 - width;
             let mut y = mouse_pos.1;
@@ -129,8 +169,14 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
     }
 }
 
-fn inventory_frame(ctx: &mut Rltk, count: usize , x: i32, y: i32, w: i32, fg: RGB, bg: RGB, ctrl_fg: RGB, action: &str) {
-    let bg_rect = Rect::with_size(x, y - 2, w, (count + 3) as i32);
+/// Where a list menu of `count` entries goes: in the middle of the map, with room for a
+/// title row and a controls row.
+fn menu_panel(ctx: &Rltk, width: i32, count: usize, extra_rows: i32) -> Panel {
+    Hud::new(ctx).map.place(Anchor::Center, width, count as i32 + 4 + extra_rows)
+}
+
+fn inventory_frame(ctx: &mut Rltk, panel: Panel, fg: RGB, bg: RGB, ctrl_fg: RGB, action: &str) {
+    let (x, right, bottom) = (panel.x, panel.right(), panel.bottom());
     let menu_text: &str;
 
     if action == "unequip" {
@@ -146,13 +192,12 @@ fn inventory_frame(ctx: &mut Rltk, count: usize , x: i32, y: i32, w: i32, fg: RG
     let start_char = rltk::to_cp437('┤');
     let end_char = rltk::to_cp437('├');
 
-    ctx.fill_region(bg_rect, rltk::to_cp437(' '), fg, bg);
-    ctx.draw_hollow_box(x, y - 2, w, (count + 3) as i32, fg, bg);
-    ctx.print_color(x + 1, y - 2, bg, fg, menu_text);
-    ctx.print_color(x + 15, y + count as i32 + 1, ctrl_fg, bg, " ESC ");
-    ctx.print_color(x + 20, y + count as i32 + 1, fg, bg, "to cancel ");
-    ctx.set(x + 14, y + count as i32 + 1, fg, bg, start_char);
-    ctx.set(x + 30, y + count as i32 + 1, fg, bg, end_char);
+    panel.draw_box(ctx, fg, bg);
+    ctx.print_color(x + 1, panel.y, bg, fg, menu_text);
+    ctx.set(right - 18, bottom, fg, bg, start_char);
+    ctx.print_color(right - 17, bottom, ctrl_fg, bg, " ESC ");
+    ctx.print_color(right - 12, bottom, fg, bg, "to cancel ");
+    ctx.set(right - 2, bottom, fg, bg, end_char);
 
 }
 
@@ -166,9 +211,36 @@ fn inventory_selection(ctx: &mut Rltk, x: i32, y: i32, fg: RGB, bg: RGB, ctrl_fg
 }
 
 #[derive(PartialEq, Copy, Clone)]
-pub enum ItemMenuResult { Cancel, NoResponse, Selected }
+pub enum ItemMenuResult { Cancel, NoResponse, Selected, Highlight { selection: usize } }
+
+/// What the player did in a list menu: picked an entry by its letter or with the cursor,
+/// moved the cursor, or pressed one of the menu's other controls.
+enum MenuInput { Nothing, Cancel, SwitchMode, Highlight(usize), Select(usize) }
+
+fn menu_input(ecs: &World, count: usize, selection: usize) -> MenuInput {
+    match input::action(ecs, Context::Menu) {
+        Some(Action::Cancel) => return MenuInput::Cancel,
+        Some(Action::SwitchMode) => return MenuInput::SwitchMode,
+        Some(Action::MenuUp) if count > 0 => return MenuInput::Highlight((selection + count - 1) % count),
+        Some(Action::MenuDown) if count > 0 => return MenuInput::Highlight((selection + 1) % count),
+        Some(Action::Confirm) if selection < count => return MenuInput::Select(selection),
+        _ => {}
+    }
+    match ecs.fetch::<Input>().key {
+        None => MenuInput::Nothing,
+        Some(key) => {
+            let letter = rltk::letter_to_option(key);
+            if letter > -1 && letter < count as i32 { MenuInput::Select(letter as usize) } else { MenuInput::Nothing }
+        }
+    }
+}
 
-pub fn show_drop_use_inventory(gs: &mut State, ctx: &mut Rltk, action: &str) -> (ItemMenuResult, Option<Entity>) {
+/// Marks the row the menu cursor is on.
+fn menu_cursor(ctx: &mut Rltk, x: i32, y: i32, ctrl_fg: RGB, bg: RGB) {
+    ctx.set(x, y, ctrl_fg, bg, rltk::to_cp437('►'));
+}
+
+pub fn show_drop_use_inventory(gs: &mut State, ctx: &mut Rltk, action: &str, selection: usize) -> (ItemMenuResult, Option<Entity>) {
     let player_entity = gs.ecs.fetch::<Entity>();
     let names = gs.ecs.read_storage::<Name>();
     let backpack = gs.ecs.read_storage::<InBackpack>();
@@ -176,10 +248,11 @@ pub fn show_drop_use_inventory(gs: &mut State, ctx: &mut Rltk, action: &str) ->
 
     let inventory = (&backpack, &names).join().filter(|item| item.0.owner == *player_entity );
     let count = inventory.count();
+    let selection = usize::min(selection, count.saturating_sub(1));
 
-    let x: i32 = 15;
-    let mut y = (25 - (count / 2)) as i32;
-    let w: i32 = 31;
+    let panel = menu_panel(ctx, 32, count, 0);
+    let x = panel.x;
+    let mut y = panel.y + 2;
 
     let fg: RGB;
     let bg: RGB;
@@ -196,13 +269,14 @@ pub fn show_drop_use_inventory(gs: &mut State, ctx: &mut Rltk, action: &str) ->
         bg = return_rgb(DEFAULT_BG);
     }
 
-    inventory_frame(ctx, count, x, y, w, fg, bg, ctrl_fg, action);
+    inventory_frame(ctx, panel, fg, bg, ctrl_fg, action);
     
 
     let mut equippable: Vec<Entity> = Vec::new();
     let mut j = 0;
     for (entity, _pack, name) in (&entities, &backpack, &names).join().filter(|item| item.1.owner == *player_entity) {
         let glyph = 97 + j;
+        if j as usize == selection { menu_cursor(ctx, x + 1, y, ctrl_fg, bg); }
         inventory_selection(ctx, x + 2, y, fg, bg, ctrl_fg, glyph, &name.name.to_string());
 
         equippable.push(entity);
@@ -210,25 +284,16 @@ pub fn show_drop_use_inventory(gs: &mut State, ctx: &mut Rltk, action: &str) ->
         j += 1; 
     }
 
-    match ctx.key {
-        None => (ItemMenuResult::NoResponse, None),
-        Some(key) => {
-            match key {
-                VirtualKeyCode::Escape => { (ItemMenuResult::Cancel, None) }
-                _ => {
-                    let selection = rltk::letter_to_option(key);
-                    if selection > -1 && selection < count as i32 {
-                        return (ItemMenuResult::Selected, Some(equippable[selection as usize]));
-                    }
-                    (ItemMenuResult::NoResponse, None)
-                }
-            }
-        }
+    match menu_input(&gs.ecs, count, selection) {
+        MenuInput::Cancel => (ItemMenuResult::Cancel, None),
+        MenuInput::Highlight(selection) => (ItemMenuResult::Highlight { selection }, None),
+        MenuInput::Select(selection) => (ItemMenuResult::Selected, Some(equippable[selection])),
+        _ => (ItemMenuResult::NoResponse, None)
     }
 
 }
 
-pub fn remove_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
+pub fn remove_item_menu(gs: &mut State, ctx: &mut Rltk, selection: usize) -> (ItemMenuResult, Option<Entity>) {
     let player_entity = gs.ecs.fetch::<Entity>();
     let names = gs.ecs.read_storage::<Name>();
     let backpack = gs.ecs.read_storage::<Equipped>();
@@ -236,21 +301,23 @@ pub fn remove_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Opti
 
     let inventory = (&backpack, &names).join().filter(|item| item.0.owner == *player_entity );
     let count = inventory.count();
+    let selection = usize::min(selection, count.saturating_sub(1));
 
     let fg: RGB = return_rgb(MENU_FG);
     let bg: RGB = return_rgb(REMOVE_BG);
     let ctrl_fg: RGB = return_rgb(CTRL_FG);
     
-    let x: i32 = 15;
-    let mut y = (25 - (count / 2)) as i32;
-    let w: i32 = 31;
+    let panel = menu_panel(ctx, 32, count, 0);
+    let x = panel.x;
+    let mut y = panel.y + 2;
 
-    inventory_frame(ctx, count, x, y, w, fg, bg, ctrl_fg, "unequip");
+    inventory_frame(ctx, panel, fg, bg, ctrl_fg, "unequip");
 
     let mut equippable: Vec<Entity> = Vec::new();
     let mut j = 0;
     for (entity, _pack, name) in (&entities, &backpack, &names).join().filter(|item| item.1.owner == *player_entity) {
         let glyph = 97 + j;
+        if j as usize == selection { menu_cursor(ctx, x + 1, y, ctrl_fg, bg); }
         inventory_selection(ctx, x + 2, y, fg, bg, ctrl_fg, glyph, &name.name.to_string());
 
         equippable.push(entity);
@@ -258,22 +325,105 @@ pub fn remove_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Opti
         j += 1; 
     }
 
-    match ctx.key {
-        None => (ItemMenuResult::NoResponse, None),
-        Some(key) => {
-            match key {
-                VirtualKeyCode::Escape => { (ItemMenuResult::Cancel, None) }
-                _ => {
-                    let selection = rltk::letter_to_option(key);
-                    if selection > -1 && selection < count as i32 {
-                        return (ItemMenuResult::Selected, Some(equippable[selection as usize]));
-                    }
-                    (ItemMenuResult::NoResponse, None)
-                }
-            }
+    match menu_input(&gs.ecs, count, selection) {
+        MenuInput::Cancel => (ItemMenuResult::Cancel, None),
+        MenuInput::Highlight(selection) => (ItemMenuResult::Highlight { selection }, None),
+        MenuInput::Select(selection) => (ItemMenuResult::Selected, Some(equippable[selection])),
+        _ => (ItemMenuResult::NoResponse, None)
+    }
+
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum VendorMode { Buy, Sell }
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum VendorResult { NoResponse, Cancel, SwitchMode, Buy, Sell, Highlight { selection: usize } }
+
+/// Shows either the vendor's stock or the player's sellable items. Returns the chosen item and its price.
+pub fn show_vendor(gs: &mut State, ctx: &mut Rltk, vendor: Entity, mode: VendorMode, selection: usize) -> (VendorResult, Option<Entity>, i32) {
+    let player_entity = gs.ecs.fetch::<Entity>();
+    let names = gs.ecs.read_storage::<Name>();
+    let backpack = gs.ecs.read_storage::<InBackpack>();
+    let values = gs.ecs.read_storage::<ItemValue>();
+    let wallets = gs.ecs.read_storage::<Wallet>();
+    let entities = gs.ecs.entities();
+
+    let owner = match mode {
+        VendorMode::Buy => vendor,
+        VendorMode::Sell => *player_entity
+    };
+
+    // Merchants sell at full value and buy back at half
+    let mut stock: Vec<(Entity, String, i32)> = Vec::new();
+    for (entity, pack, name, value) in (&entities, &backpack, &names, &values).join() {
+        if pack.owner == owner {
+            let price = match mode {
+                VendorMode::Buy => value.value,
+                VendorMode::Sell => i32::max(1, value.value / 2)
+            };
+            stock.push((entity, name.name.to_string(), price));
         }
     }
+    let count = stock.len();
+    let selection = usize::min(selection, count.saturating_sub(1));
 
+    let fg: RGB = return_rgb(MENU_FG);
+    let bg: RGB = return_rgb(SHOP_BG);
+    let ctrl_fg: RGB = return_rgb(CTRL_FG);
+    let gold_fg: RGB = return_rgb(GOLD_FG);
+
+    // An extra row under the stock for the player's gold
+    let panel = menu_panel(ctx, 41, count, 1);
+    let x = panel.x;
+    let mut y = panel.y + 2;
+
+    let vendor_name = match names.get(vendor) {
+        None => "Merchant".to_string(),
+        Some(name) => name.name.to_string()
+    };
+    let title = match mode {
+        VendorMode::Buy => format!(" {} - Buy Which Item? ", vendor_name),
+        VendorMode::Sell => format!(" {} - Sell Which Item? ", vendor_name)
+    };
+    let gold = match wallets.get(*player_entity) {
+        None => 0,
+        Some(wallet) => wallet.gold
+    };
+
+    panel.draw_box(ctx, fg, bg);
+    ctx.print_color(x + 1, panel.y, bg, fg, title);
+    ctx.print_color(x + 2, y + count as i32, gold_fg, bg, format!("You have {} gold.", gold));
+    ctx.print_color(x + 2, panel.bottom(), ctrl_fg, bg, " TAB ");
+    ctx.print_color(x + 7, panel.bottom(), fg, bg, "buy/sell ");
+    ctx.print_color(x + 18, panel.bottom(), ctrl_fg, bg, " ESC ");
+    ctx.print_color(x + 23, panel.bottom(), fg, bg, "to leave ");
+
+    let mut j = 0;
+    for (_entity, name, price) in stock.iter() {
+        let glyph = 97 + j;
+        if j as usize == selection { menu_cursor(ctx, x + 1, y, ctrl_fg, bg); }
+        inventory_selection(ctx, x + 2, y, fg, bg, ctrl_fg, glyph, name);
+        let price_text = format!("{}g", price);
+        ctx.print_color(panel.right() - 1 - price_text.len() as i32, y, gold_fg, bg, &price_text);
+
+        y += 1;
+        j += 1;
+    }
+
+    match menu_input(&gs.ecs, count, selection) {
+        MenuInput::Nothing => (VendorResult::NoResponse, None, 0),
+        MenuInput::Cancel => (VendorResult::Cancel, None, 0),
+        MenuInput::SwitchMode => (VendorResult::SwitchMode, None, 0),
+        MenuInput::Highlight(selection) => (VendorResult::Highlight { selection }, None, 0),
+        MenuInput::Select(selection) => {
+            let (item, _name, price) = &stock[selection];
+            match mode {
+                VendorMode::Buy => (VendorResult::Buy, Some(*item), *price),
+                VendorMode::Sell => (VendorResult::Sell, Some(*item), *price)
+            }
+        }
+    }
 }
 
 #[derive(PartialEq, Copy, Clone)]
@@ -281,8 +431,8 @@ pub enum GameOverResult {
     NoSelection, QuitToMenu    
 }
 
-pub fn game_over(ctx: &mut Rltk) -> GameOverResult {
-    let y: i32 = 15;
+pub fn game_over(ecs: &World, ctx: &mut Rltk) -> GameOverResult {
+    let y = Panel::screen(ctx).place(Anchor::Center, 0, 5).y;
     let game_over_fg: RGB = return_rgb(GAME_OVER_FG);
     let fg: RGB = return_rgb(DEFAULT_FG);
     let bg: RGB = return_rgb(DEFAULT_BG);
@@ -293,59 +443,145 @@ pub fn game_over(ctx: &mut Rltk) -> GameOverResult {
 
     ctx.print_color_centered(y + 4, ctrl_fg, bg, "Press any key to return to the menu.");
 
-    match ctx.key {
-        None => GameOverResult::NoSelection,
-        Some(_) => GameOverResult::QuitToMenu
+    if ecs.fetch::<Input>().pressed() { GameOverResult::QuitToMenu } else { GameOverResult::NoSelection }
+
+}
+
+pub fn victory(ecs: &World, ctx: &mut Rltk) -> GameOverResult {
+    let victory_fg: RGB = return_rgb(VICTORY_FG);
+    let fg: RGB = return_rgb(DEFAULT_FG);
+    let bg: RGB = return_rgb(DEFAULT_BG);
+    let ctrl_fg: RGB = return_rgb(CTRL_FG);
+
+    let run_stats = ecs.fetch::<RunStats>();
+    let player_entity = ecs.fetch::<Entity>();
+    let wallets = ecs.read_storage::<Wallet>();
+    let gold = match wallets.get(*player_entity) {
+        None => 0,
+        Some(wallet) => wallet.gold
+    };
+
+    let height = if run_stats.bosses_killed.is_empty() { 10 } else { 12 };
+    let y = Panel::screen(ctx).place(Anchor::Center, 0, height).y;
+
+    ctx.print_color_centered(y, victory_fg, bg, "Victory!");
+    ctx.print_color_centered(y + 2, fg, bg, "You have completed your Quest to Collect the McGuffin");
+
+    ctx.print_color_centered(y + 4, fg, bg, format!("Turns taken: {}", run_stats.turns));
+    ctx.print_color_centered(y + 5, fg, bg, format!("Monsters slain: {}", run_stats.kills));
+    ctx.print_color_centered(y + 6, fg, bg, format!("Deepest depth reached: {}", run_stats.deepest_depth));
+    ctx.print_color_centered(y + 7, fg, bg, format!("Gold carried: {}", gold));
+
+    let mut line = y + 9;
+    if !run_stats.bosses_killed.is_empty() {
+        ctx.print_color_centered(line, fg, bg, format!("Bosses slain: {}", run_stats.bosses_killed.join(", ")));
+        line += 2;
     }
 
+    ctx.print_color_centered(line, ctrl_fg, bg, "Press any key to return to the menu.");
+
+    if ecs.fetch::<Input>().pressed() { GameOverResult::QuitToMenu } else { GameOverResult::NoSelection }
 }
 
-pub fn ranged_target(gs: &mut State, ctx: &mut Rltk, range: i32) -> (ItemMenuResult, Option<Point>) {
-    let player_entity = gs.ecs.fetch::<Entity>();
+/// Highlights a map tile, if it is on screen.
+fn set_map_bg(ctx: &mut Rltk, viewport: &Viewport, x: i32, y: i32, color: RGB) {
+    if let Some((screen_x, screen_y)) = viewport.to_screen(x, y) {
+        ctx.set_bg(screen_x, screen_y, color);
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum TargetingResult { Cancel, Aiming { target: Option<(i32, i32)> }, Fire { x: i32, y: i32 } }
+
+/// Picks a tile in range with the mouse, or by moving a reticle around and confirming.
+/// Cycling jumps the reticle between monsters in sight, nearest first.
+pub fn ranged_target(gs: &mut State, ctx: &mut Rltk, range: i32, item: Entity, target: Option<(i32, i32)>) -> TargetingResult {
+    let available_cells = targeting::tiles_in_range(&gs.ecs, range);
+    if available_cells.is_empty() { return TargetingResult::Cancel; }
+    let hostiles = targeting::hostiles(&gs.ecs, &available_cells);
+    let (target_x, target_y) = target.unwrap_or_else(|| targeting::first_target(&gs.ecs, &hostiles));
+
+    let viewport = Viewport::new(&gs.ecs, ctx);
+    let map = gs.ecs.fetch::<Map>();
     let player_pos = gs.ecs.fetch::<Point>();
-    let viewsheds = gs.ecs.read_storage::<Viewshed>();
+    let area_of_effect = gs.ecs.read_storage::<AreaOfEffect>();
 
     // Targeting message
     let fg: RGB = return_rgb(CTRL_FG);
     let bg: RGB = return_rgb(DEFAULT_BG);
     let msg = "Select Target";
-    ctx.print_color(5, 0, fg, bg, msg);
+    let area = Hud::new(ctx).map;
+    ctx.print_color(area.x + 1, area.y, fg, bg, msg);
 
     // Highlight available target cells
     let target_bg: RGB = return_rgb(TARGET_BG);
-    let mut available_cells = Vec::new();
-    let visible = viewsheds.get(*player_entity);
-    if let Some(visible) = visible {
-        // We have a viewshed
-        for idx in visible.visible_tiles.iter() {
-            let distance = rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, *idx);
-            if distance <= range as f32 {
-                ctx.set_bg(idx.x, idx.y, target_bg);
-                available_cells.push(idx);
+    for idx in available_cells.iter() {
+        set_map_bg(ctx, &viewport, idx.x, idx.y, target_bg);
+    }
+    // The reticle starts on the player when nothing is in range, and they can't aim at themselves
+    let in_range = |x: i32, y: i32| (x, y) != (player_pos.x, player_pos.y) && available_cells.iter().any(|idx| idx.x == x && idx.y == y);
+
+    // Preview the blast and the projectile's flight
+    if let Some(aoe) = area_of_effect.get(item) {
+        let blast_bg: RGB = return_rgb(BLAST_BG);
+        for tile in rltk::field_of_view(Point::new(target_x, target_y), aoe.radius, &*map).iter() {
+            if tile.x >= 0 && tile.x < map.width && tile.y >= 0 && tile.y < map.height {
+                set_map_bg(ctx, &viewport, tile.x, tile.y, blast_bg);
             }
         }
-    } else {
-        return (ItemMenuResult::Cancel, None);
+    }
+    let path_bg: RGB = return_rgb(PATH_BG);
+    for tile in targeting::projectile_path(*player_pos, Point::new(target_x, target_y)).iter() {
+        set_map_bg(ctx, &viewport, tile.x, tile.y, path_bg);
     }
 
-    // Draw mouse cursor
+    // Draw the reticle
     let valid_bg: RGB = return_rgb(MOUSE_BG);
     let invalid_bg: RGB = return_rgb(ERROR_BG);
+    set_map_bg(ctx, &viewport, target_x, target_y, if in_range(target_x, target_y) { valid_bg } else { invalid_bg });
+
+    match input::action(&gs.ecs, Context::Menu) {
+        Some(Action::Cancel) => return TargetingResult::Cancel,
+        Some(Action::SwitchMode) => {
+            if hostiles.is_empty() { return TargetingResult::Aiming { target: Some((target_x, target_y)) }; }
+            let next = match hostiles.iter().position(|(_, pos)| pos.x == target_x && pos.y == target_y) {
+                None => 0,
+                Some(i) => (i + 1) % hostiles.len()
+            };
+            return TargetingResult::Aiming { target: Some((hostiles[next].1.x, hostiles[next].1.y)) };
+        }
+        Some(Action::Confirm) => {
+            if in_range(target_x, target_y) {
+                targeting::remember(&gs.ecs, target_x, target_y);
+                return TargetingResult::Fire { x: target_x, y: target_y };
+            }
+            return TargetingResult::Aiming { target: Some((target_x, target_y)) };
+        }
+        _ => {}
+    }
+
+    // Move the reticle
+    if let Some((delta_x, delta_y)) = input::action(&gs.ecs, Context::Game).and_then(|action| action.direction()) {
+        let (x, y) = viewport.clamp(&map, target_x + delta_x, target_y + delta_y);
+        return TargetingResult::Aiming { target: Some((x, y)) };
+    }
+
+    // Draw mouse cursor
     let mouse_pos = ctx.mouse_pos();
-    let mut valid_target = false;
-    for idx in available_cells.iter() { if idx.x == mouse_pos.0 && idx.y == mouse_pos.1 { valid_target = true; } }
-    if valid_target {
+    let mouse_tile = viewport.to_world(&map, mouse_pos.0, mouse_pos.1).filter(|(x, y)| in_range(*x, *y));
+    if let Some((x, y)) = mouse_tile {
         ctx.set_bg(mouse_pos.0, mouse_pos.1, valid_bg);
         if ctx.left_click {
-            return (ItemMenuResult::Selected, Some(Point::new(mouse_pos.0, mouse_pos.1)));
+            targeting::remember(&gs.ecs, x, y);
+            return TargetingResult::Fire { x, y };
         }
     } else {
         ctx.set_bg(mouse_pos.0, mouse_pos.1, invalid_bg);
         if ctx.left_click {
-            return (ItemMenuResult::Cancel, None);
+            return TargetingResult::Cancel;
         }
     }
-    (ItemMenuResult::NoResponse, None)
+    TargetingResult::Aiming { target: Some((target_x, target_y)) }
 }
 
 #[derive(PartialEq, Copy, Clone)]
@@ -371,17 +607,19 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
 
     let sel_glyph = rltk::to_cp437('►');
 
+    // The title art is drawn for an 80x50 console, so the menu is laid out against it
+    let art = Panel::screen(ctx).place(Anchor::Center, 80, 50);
     let assets = gs.ecs.fetch::<RexAssets>();
-    ctx.render_xp_sprite(&assets.menu, 0, 0);
+    ctx.render_xp_sprite(&assets.menu, art.x, art.y);
 
-    let x: i32 = 5;
-    let mut y = 23;
-    let by_y: i32 = 27;
+    let x: i32 = art.x + 5;
+    let mut y = art.y + 23;
+    let by_y: i32 = art.y + 27;
     
-    ctx.print_color(52, by_y, title_fg, bg, "By u/usrTaken");
-    ctx.print_color(52-5, by_y + 1, notselet_fg, bg, "_______________________");
-    ctx.print_color_centered_at(60, by_y + 3, select_fg, bg,"Based upon the Rust Roguelike Tutorial" );
-    ctx.print_color_centered_at(59, by_y + 4, title_fg, bg, "By u/thebracket");
+    ctx.print_color(art.x + 52, by_y, title_fg, bg, "By u/usrTaken");
+    ctx.print_color(art.x + 52-5, by_y + 1, notselet_fg, bg, "_______________________");
+    ctx.print_color_centered_at(art.x + 60, by_y + 3, select_fg, bg,"Based upon the Rust Roguelike Tutorial" );
+    ctx.print_color_centered_at(art.x + 59, by_y + 4, title_fg, bg, "By u/thebracket");
 
     ctx.draw_box_double(x - 3, y - 2, 27, 11, title_fg, bg);
     ctx.print_color(x - 1, y, select_fg, bg, "Use ▲/▼ arrows and Enter");
@@ -412,42 +650,476 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
             ctx.print_color(x + 1, y, notselet_fg, bg, "Quit");
         }
 
-        match ctx.key {
-            None => return MainMenuResult::NoSelection { selected: selection },
-            Some(key) => {
-                match key {
-                    VirtualKeyCode::Escape => { return MainMenuResult::NoSelection { selected: MainMenuSelection::Quit } }
-                    VirtualKeyCode::Up => {
-                        let mut newselection;
-                        match selection {
-                            MainMenuSelection::NewGame => newselection = MainMenuSelection::Quit,
-                            MainMenuSelection::LoadGame => newselection = MainMenuSelection::NewGame,
-                            MainMenuSelection::Quit => newselection = MainMenuSelection::LoadGame
-                        }
-                        if newselection == MainMenuSelection::LoadGame && !save_exists {
-                            newselection = MainMenuSelection::NewGame;
-                        }
-                        return MainMenuResult::NoSelection { selected: newselection }
+        match input::action(&gs.ecs, Context::Menu) {
+            Some(Action::Cancel) => { return MainMenuResult::NoSelection { selected: MainMenuSelection::Quit } }
+            Some(Action::MenuUp) => {
+                let mut newselection;
+                match selection {
+                    MainMenuSelection::NewGame => newselection = MainMenuSelection::Quit,
+                    MainMenuSelection::LoadGame => newselection = MainMenuSelection::NewGame,
+                    MainMenuSelection::Quit => newselection = MainMenuSelection::LoadGame
+                }
+                if newselection == MainMenuSelection::LoadGame && !save_exists {
+                    newselection = MainMenuSelection::NewGame;
+                }
+                return MainMenuResult::NoSelection { selected: newselection }
+            }
+            Some(Action::MenuDown) => {
+                let mut newselection;
+                match selection {
+                    MainMenuSelection::NewGame => newselection = MainMenuSelection::LoadGame,
+                    MainMenuSelection::LoadGame => newselection = MainMenuSelection::Quit,
+                    MainMenuSelection::Quit => newselection = MainMenuSelection::NewGame
+                }
+                if newselection == MainMenuSelection::LoadGame && !save_exists {
+                    newselection = MainMenuSelection::Quit;
+                }
+                return MainMenuResult::NoSelection { selected: newselection }
+            }
+            Some(Action::Confirm) => return MainMenuResult::Selected { selected: selection },
+            _ => return MainMenuResult::NoSelection { selected: selection }
+        }
+    }
+
+    MainMenuResult::NoSelection { selected: MainMenuSelection::NewGame }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum ExamineResult { Cancel, Looking { x: i32, y: i32 } }
+
+/// Sums the melee power and defense bonuses of everything `owner` has equipped.
+fn equipment_bonuses(ecs: &World, owner: Entity) -> (i32, i32) {
+    let equipped = ecs.read_storage::<Equipped>();
+    let power_bonuses = ecs.read_storage::<MeleePowerBonus>();
+    let defense_bonuses = ecs.read_storage::<DefenseBonus>();
+
+    let mut power = 0;
+    let mut defense = 0;
+    for (equipped_by, bonus) in (&equipped, &power_bonuses).join() {
+        if equipped_by.owner == owner { power += bonus.power; }
+    }
+    for (equipped_by, bonus) in (&equipped, &defense_bonuses).join() {
+        if equipped_by.owner == owner { defense += bonus.defense; }
+    }
+    (power, defense)
+}
+
+fn health_estimate(stats: &CombatStats) -> &'static str {
+    let percent = stats.hp as f32 / stats.max_hp as f32;
+    if percent >= 1.0 { "Unhurt" }
+    else if percent > 0.75 { "Scratched" }
+    else if percent > 0.5 { "Wounded" }
+    else if percent > 0.25 { "Badly wounded" }
+    else { "Near death" }
+}
+
+/// Compares how many hits each side needs to finish the other off.
+fn threat_level(ecs: &World, target: &CombatStats) -> (&'static str, RGB) {
+    let player_entity = ecs.fetch::<Entity>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    let player_stats = combat_stats.get(*player_entity);
+    let player_stats = match player_stats {
+        None => return ("Unknown", return_rgb(DEFAULT_FG)),
+        Some(stats) => stats
+    };
+    let (power_bonus, defense_bonus) = equipment_bonuses(ecs, *player_entity);
+
+    let damage_dealt = i32::max(0, (player_stats.power + power_bonus) - target.defense);
+    let damage_taken = i32::max(0, target.power - (player_stats.defense + defense_bonus));
+
+    if damage_taken == 0 { return ("Harmless", return_rgb(THREAT_LOW)); }
+    if damage_dealt == 0 { return ("Deadly", return_rgb(THREAT_DEADLY)); }
+
+    let hits_to_kill = (target.hp + damage_dealt - 1) / damage_dealt;
+    let hits_to_die = (player_stats.hp + damage_taken - 1) / damage_taken;
+    let ratio = hits_to_die as f32 / hits_to_kill as f32;
+
+    if ratio >= 4.0 { ("Trivial", return_rgb(THREAT_LOW)) }
+    else if ratio >= 2.0 { ("Low", return_rgb(THREAT_LOW)) }
+    else if ratio >= 1.0 { ("Moderate", return_rgb(THREAT_MODERATE)) }
+    else if ratio >= 0.5 { ("High", return_rgb(THREAT_HIGH)) }
+    else { ("Deadly", return_rgb(THREAT_DEADLY)) }
+}
+
+pub fn examine(gs: &mut State, ctx: &mut Rltk, x: i32, y: i32) -> ExamineResult {
+    let map = gs.ecs.fetch::<Map>();
+    let names = gs.ecs.read_storage::<Name>();
+    let descriptions = gs.ecs.read_storage::<Description>();
+    let hidden = gs.ecs.read_storage::<Hidden>();
+    let monsters = gs.ecs.read_storage::<Monster>();
+    let combat_stats = gs.ecs.read_storage::<CombatStats>();
+    let asleep = gs.ecs.read_storage::<Asleep>();
+
+    let fg: RGB = return_rgb(DEFAULT_FG);
+    let bg: RGB = return_rgb(TOOLTIP_BG);
+    let ctrl_fg: RGB = return_rgb(CTRL_FG);
+    const TEXT_WIDTH: usize = 34;
+
+    // Draw the look cursor
+    let viewport = Viewport::new(&gs.ecs, ctx);
+    set_map_bg(ctx, &viewport, x, y, return_rgb(LOOK_BG));
+
+    // Describe the tile under the cursor
+    let mut lines: Vec<(String, RGB)> = Vec::new();
+    let idx = map.xy_idx(x, y);
+    if !map.revealed_tiles[idx] {
+        lines.push(("You haven't explored there.".to_string(), fg));
+    } else {
+        let tile_terrain = terrain(map.tiles[idx]);
+        let (tile_name, tile_text) = (tile_terrain.name, tile_terrain.description);
+        if !map.visible_tiles[idx] {
+            lines.push((format!("{} (remembered)", tile_name), ctrl_fg));
+        } else {
+            lines.push((tile_name.to_string(), ctrl_fg));
+            for line in layout::wrap_text(tile_text, TEXT_WIDTH) { lines.push((line, fg)); }
+            if map.wall_damage.contains_key(&idx) { lines.push(("It is cracked and crumbling.".to_string(), fg)); }
+
+            for entity in map.tile_content[idx].iter() {
+                if hidden.get(*entity).is_some() { continue; }
+                if let Some(name) = names.get(*entity) {
+                    lines.push((String::new(), fg));
+                    lines.push((name.name.to_string(), ctrl_fg));
+
+                    if let (Some(_monster), Some(stats)) = (monsters.get(*entity), combat_stats.get(*entity)) {
+                        lines.push((format!("Health: {}", health_estimate(stats)), fg));
+                        let (threat, threat_fg) = threat_level(&gs.ecs, stats);
+                        lines.push((format!("Threat: {}", threat), threat_fg));
+                        if asleep.get(*entity).is_some() { lines.push(("It is asleep.".to_string(), fg)); }
                     }
-                    VirtualKeyCode::Down => {
-                        let mut newselection;
-                        match selection {
-                            MainMenuSelection::NewGame => newselection = MainMenuSelection::LoadGame,
-                            MainMenuSelection::LoadGame => newselection = MainMenuSelection::Quit,
-                            MainMenuSelection::Quit => newselection = MainMenuSelection::NewGame
-                        }
-                        if newselection == MainMenuSelection::LoadGame && !save_exists {
-                            newselection = MainMenuSelection::Quit;
-                        }
-                        return MainMenuResult::NoSelection { selected: newselection }
+
+                    if let Some(description) = descriptions.get(*entity) {
+                        for line in layout::wrap_text(&description.description, TEXT_WIDTH) { lines.push((line, fg)); }
                     }
-                    VirtualKeyCode::Return => return MainMenuResult::Selected { selected: selection },
-                    _ => return MainMenuResult::NoSelection { selected: selection }
                 }
             }
-            
         }
     }
 
-    MainMenuResult::NoSelection { selected: MainMenuSelection::NewGame }
-}
\ No newline at end of file
+    // Draw the description box on the opposite side of the map from the cursor
+    let mut width: i32 = 14;
+    for (line, _fg) in lines.iter() {
+        if width < line.len() as i32 { width = line.len() as i32; }
+    }
+    width += 4;
+    let height = lines.len() as i32 + 2;
+    let anchor = if x - viewport.min_x > viewport.width / 2 { Anchor::TopLeft } else { Anchor::TopRight };
+    let panel = Hud::new(ctx).map.inner().place(anchor, width, height);
+
+    panel.draw_box(ctx, fg, bg);
+    ctx.print_color(panel.x + 1, panel.y, bg, fg, " Look ");
+    let mut line_y = panel.y + 1;
+    for (line, line_fg) in lines.iter() {
+        ctx.print_color(panel.x + 2, line_y, *line_fg, bg, line);
+        line_y += 1;
+    }
+    ctx.print_color(panel.x + 1, panel.bottom(), ctrl_fg, bg, " ESC ");
+    ctx.print_color(panel.x + 6, panel.bottom(), fg, bg, "to exit ");
+
+    // Move the cursor
+    let move_cursor = |delta_x: i32, delta_y: i32| {
+        let (x, y) = viewport.clamp(&map, x + delta_x, y + delta_y);
+        ExamineResult::Looking { x, y }
+    };
+
+    if input::action(&gs.ecs, Context::Menu) == Some(Action::Cancel) { return ExamineResult::Cancel; }
+    match input::action(&gs.ecs, Context::Game) {
+        Some(Action::Examine) => ExamineResult::Cancel,
+        Some(action) => match action.direction() {
+            Some((delta_x, delta_y)) => move_cursor(delta_x, delta_y),
+            None => ExamineResult::Looking { x, y }
+        },
+        None => ExamineResult::Looking { x, y }
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum KeybindingsResult { Done, Showing { selection: usize, waiting: bool } }
+
+/// Lists every action and the keys bound to it. Enter rebinds the highlighted action to the
+/// next key pressed, Backspace clears it and F1-F3 load a preset. Escape always leaves, so
+/// the screen can't be locked shut by unbinding Cancel.
+pub fn keybindings(gs: &mut State, ctx: &mut Rltk, selection: usize, waiting: bool) -> KeybindingsResult {
+    let mut keymap = gs.ecs.fetch_mut::<Keymap>();
+
+    let fg: RGB = return_rgb(MENU_FG);
+    let bg: RGB = return_rgb(INV_BG);
+    let ctrl_fg: RGB = return_rgb(CTRL_FG);
+    let select_fg: RGB = return_rgb(SELECT_FG);
+
+    let panel = Panel::screen(ctx).place(Anchor::Center, 61, Action::ALL.len() as i32 + 6);
+    let x = panel.x;
+    let y = panel.y + 2;
+
+    panel.draw_box(ctx, fg, bg);
+    ctx.print_color(x + 1, panel.y, bg, fg, " Keybindings ");
+
+    for (i, action) in Action::ALL.iter().enumerate() {
+        let row = y + i as i32;
+        let row_fg = if i == selection { select_fg } else { fg };
+        if i == selection { ctx.set(x + 1, row, ctrl_fg, bg, rltk::to_cp437('►')); }
+        ctx.print_color(x + 3, row, row_fg, bg, action.name());
+        let keys = if i == selection && waiting { "press a key...".to_string() } else { keymap.describe(*action) };
+        ctx.print_color(x + 24, row, row_fg, bg, &keys);
+    }
+
+    let help_y = y + Action::ALL.len() as i32 + 1;
+    ctx.print_color(x + 2, help_y, ctrl_fg, bg, " ENTER ");
+    ctx.print_color(x + 9, help_y, fg, bg, "rebind ");
+    ctx.print_color(x + 17, help_y, ctrl_fg, bg, " BACKSPACE ");
+    ctx.print_color(x + 28, help_y, fg, bg, "clear ");
+    ctx.print_color(x + 35, help_y, ctrl_fg, bg, " ESC ");
+    ctx.print_color(x + 40, help_y, fg, bg, "done ");
+    ctx.print_color(x + 2, help_y + 1, ctrl_fg, bg, " F1 ");
+    ctx.print_color(x + 6, help_y + 1, fg, bg, "vi ");
+    ctx.print_color(x + 10, help_y + 1, ctrl_fg, bg, " F2 ");
+    ctx.print_color(x + 14, help_y + 1, fg, bg, "numpad ");
+    ctx.print_color(x + 22, help_y + 1, ctrl_fg, bg, " F3 ");
+    ctx.print_color(x + 26, help_y + 1, fg, bg, "WASD presets ");
+
+    // A pad can move around the list and leave, but only keys can be bound
+    let input = gs.ecs.fetch::<Input>();
+    if !waiting && input.pad.is_some() {
+        return match input.action(&keymap, Context::Menu) {
+            Some(Action::Cancel) => {
+                keymap.save(&mut gs.ecs.fetch_mut::<GameLog>());
+                KeybindingsResult::Done
+            }
+            Some(Action::MenuUp) => KeybindingsResult::Showing { selection: (selection + Action::ALL.len() - 1) % Action::ALL.len(), waiting },
+            Some(Action::MenuDown) => KeybindingsResult::Showing { selection: (selection + 1) % Action::ALL.len(), waiting },
+            _ => KeybindingsResult::Showing { selection, waiting }
+        };
+    }
+
+    let key = match ctx.key {
+        None => return KeybindingsResult::Showing { selection, waiting },
+        Some(key) => key
+    };
+
+    // The next key pressed is bound to the highlighted action; shift on its own is just a modifier
+    if waiting {
+        if key == VirtualKeyCode::LShift || key == VirtualKeyCode::RShift {
+            return KeybindingsResult::Showing { selection, waiting };
+        }
+        if key != VirtualKeyCode::Escape && !keymap.bind(KeyPress{ key, shift: ctx.shift }, Action::ALL[selection]) {
+            gs.ecs.fetch_mut::<GameLog>().push(LogCategory::General, "That key is the last one left for Cancel or Confirm.");
+        }
+        return KeybindingsResult::Showing { selection, waiting: false };
+    }
+
+    let preset = match key {
+        VirtualKeyCode::F1 => Some(Preset::Vi),
+        VirtualKeyCode::F2 => Some(Preset::Numpad),
+        VirtualKeyCode::F3 => Some(Preset::Wasd),
+        _ => None
+    };
+    if let Some(preset) = preset {
+        *keymap = Keymap::preset(preset);
+        return KeybindingsResult::Showing { selection, waiting };
+    }
+
+    match key {
+        VirtualKeyCode::Escape => {
+            keymap.save(&mut gs.ecs.fetch_mut::<GameLog>());
+            return KeybindingsResult::Done;
+        }
+        VirtualKeyCode::Back => {
+            if !keymap.unbind_action(Action::ALL[selection]) {
+                gs.ecs.fetch_mut::<GameLog>().push(LogCategory::General, "Cancel and Confirm always need a key.");
+            }
+            return KeybindingsResult::Showing { selection, waiting };
+        }
+        _ => {}
+    }
+
+    match keymap.action(Context::Menu, key, ctx.shift) {
+        Some(Action::Cancel) => {
+            keymap.save(&mut gs.ecs.fetch_mut::<GameLog>());
+            KeybindingsResult::Done
+        }
+        Some(Action::MenuUp) => KeybindingsResult::Showing { selection: (selection + Action::ALL.len() - 1) % Action::ALL.len(), waiting },
+        Some(Action::MenuDown) => KeybindingsResult::Showing { selection: (selection + 1) % Action::ALL.len(), waiting },
+        Some(Action::Confirm) => KeybindingsResult::Showing { selection, waiting: true },
+        _ => KeybindingsResult::Showing { selection, waiting }
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum MessageLogResult { Done, Showing { offset: usize, filter: Option<LogCategory> } }
+
+/// Every message still in the log, newest at the bottom. `offset` is how many messages
+/// the view has been scrolled back, and switching modes steps through the categories.
+pub fn message_log(gs: &mut State, ctx: &mut Rltk, offset: usize, filter: Option<LogCategory>) -> MessageLogResult {
+    let log = gs.ecs.fetch::<GameLog>();
+
+    let fg: RGB = return_rgb(MENU_FG);
+    let bg: RGB = return_rgb(DEFAULT_BG);
+    let ctrl_fg: RGB = return_rgb(CTRL_FG);
+    let turn_fg: RGB = return_rgb(LOG_TURN_FG);
+
+    let panel = Panel::screen(ctx).inner();
+    let (x, y) = (panel.x, panel.y);
+    let rows = (panel.height - 5) as usize;
+
+    let entries: Vec<&LogEntry> = log.entries.iter()
+        .filter(|entry| filter.filter(|category| *category != entry.category).is_none())
+        .collect();
+    let max_offset = entries.len().saturating_sub(rows);
+    let offset = usize::min(offset, max_offset);
+
+    panel.draw_box(ctx, fg, bg);
+    let title = match filter {
+        None => " Message Log - All ".to_string(),
+        Some(category) => format!(" Message Log - {} ", category.name())
+    };
+    ctx.print_color(x + 1, y, bg, fg, &title);
+
+    let first = entries.len().saturating_sub(rows + offset);
+    let mut row_y = y + 1;
+    for entry in entries.iter().skip(first).take(rows) {
+        ctx.print_color(x + 2, row_y, turn_fg, bg, format!("{:>5}", entry.turn));
+        print_log_entry(ctx, x + 9, row_y, panel.width - 12, entry, bg);
+        row_y += 1;
+    }
+
+    let help_y = panel.bottom() - 2;
+    ctx.print_color(x + 2, help_y, ctrl_fg, bg, " ▲/▼ ");
+    ctx.print_color(x + 7, help_y, fg, bg, "scroll ");
+    ctx.print_color(x + 15, help_y, ctrl_fg, bg, " TAB ");
+    ctx.print_color(x + 20, help_y, fg, bg, "filter ");
+    ctx.print_color(x + 28, help_y, ctrl_fg, bg, " ESC ");
+    ctx.print_color(x + 33, help_y, fg, bg, "close ");
+
+    match input::action(&gs.ecs, Context::Menu) {
+        Some(Action::Cancel) => MessageLogResult::Done,
+        Some(Action::MenuUp) => MessageLogResult::Showing { offset: usize::min(offset + 1, max_offset), filter },
+        Some(Action::MenuDown) => MessageLogResult::Showing { offset: offset.saturating_sub(1), filter },
+        Some(Action::SwitchMode) => {
+            let next = match filter {
+                None => Some(LogCategory::ALL[0]),
+                Some(category) => LogCategory::ALL.iter().position(|c| *c == category).and_then(|i| LogCategory::ALL.get(i + 1)).copied()
+            };
+            MessageLogResult::Showing { offset: 0, filter: next }
+        }
+        _ => MessageLogResult::Showing { offset, filter }
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum OverviewResult { Done, Showing }
+
+/// The whole explored map at once, shrunk to fit the play area.
+pub fn overview_map(gs: &mut State, ctx: &mut Rltk) -> OverviewResult {
+    let fg: RGB = return_rgb(DEFAULT_FG);
+    let bg: RGB = return_rgb(DEFAULT_BG);
+    let ctrl_fg: RGB = return_rgb(CTRL_FG);
+
+    let panel = Hud::new(ctx).map;
+    let inner = panel.inner();
+
+    panel.draw_box(ctx, fg, bg);
+    ctx.print_color(panel.x + 1, panel.y, bg, fg, " Overview ");
+    overview::draw_overview(&gs.ecs, ctx, inner.x, inner.y, inner.width, inner.height);
+    ctx.print_color(panel.x + 1, panel.bottom(), ctrl_fg, bg, " ESC ");
+    ctx.print_color(panel.x + 6, panel.bottom(), fg, bg, "to close ");
+
+    if input::action(&gs.ecs, Context::Menu) == Some(Action::Cancel) || input::action(&gs.ecs, Context::Game) == Some(Action::Overview) {
+        return OverviewResult::Done;
+    }
+    OverviewResult::Showing
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum CharacterSheetResult { Done, Showing }
+
+/// The player's attack and defense with the bonus from each piece of equipment, their hunger,
+/// whatever is affecting them and how the run is going so far.
+pub fn character_sheet(gs: &mut State, ctx: &mut Rltk) -> CharacterSheetResult {
+    let player_entity = *gs.ecs.fetch::<Entity>();
+    let map = gs.ecs.fetch::<Map>();
+    let run_stats = gs.ecs.fetch::<RunStats>();
+    let entities = gs.ecs.entities();
+    let names = gs.ecs.read_storage::<Name>();
+    let combat_stats = gs.ecs.read_storage::<CombatStats>();
+    let equipped = gs.ecs.read_storage::<Equipped>();
+    let power_bonuses = gs.ecs.read_storage::<MeleePowerBonus>();
+    let defense_bonuses = gs.ecs.read_storage::<DefenseBonus>();
+    let hunger = gs.ecs.read_storage::<HungerClock>();
+    let confusion = gs.ecs.read_storage::<Confusion>();
+    let poisoned = gs.ecs.read_storage::<Poisoned>();
+    let sneaking = gs.ecs.read_storage::<Sneaking>();
+
+    let fg: RGB = return_rgb(MENU_FG);
+    let bg: RGB = return_rgb(DEFAULT_BG);
+    let ctrl_fg: RGB = return_rgb(CTRL_FG);
+
+    let mut lines: Vec<(String, RGB)> = Vec::new();
+    if let Some(stats) = combat_stats.get(player_entity) {
+        let (power_bonus, defense_bonus) = equipment_bonuses(&gs.ecs, player_entity);
+        let hunger_bonus = hunger.get(player_entity).map_or(0, |clock| hunger_power_bonus(clock.state));
+        lines.push(("Combat".to_string(), ctrl_fg));
+        lines.push((format!("HP       {} / {}", stats.hp, stats.max_hp), fg));
+        lines.push((format!("Attack   {} base, {} effective", stats.power, stats.power + power_bonus + hunger_bonus), fg));
+        lines.push((format!("Defense  {} base, {} effective", stats.defense, stats.defense + defense_bonus), fg));
+    }
+
+    lines.push((String::new(), fg));
+    lines.push(("Equipment".to_string(), ctrl_fg));
+    let mut anything_equipped = false;
+    for (entity, equipped_by, name) in (&entities, &equipped, &names).join() {
+        if equipped_by.owner != player_entity { continue; }
+        let mut bonuses: Vec<String> = Vec::new();
+        if let Some(bonus) = power_bonuses.get(entity) { bonuses.push(format!("{:+} attack", bonus.power)); }
+        if let Some(bonus) = defense_bonuses.get(entity) { bonuses.push(format!("{:+} defense", bonus.defense)); }
+        if bonuses.is_empty() { bonuses.push("no bonus".to_string()); }
+        lines.push((format!("{:<20} {}", name.name, bonuses.join(", ")), fg));
+        anything_equipped = true;
+    }
+    if !anything_equipped { lines.push(("Nothing equipped".to_string(), fg)); }
+
+    if let Some(clock) = hunger.get(player_entity) {
+        let (hunger_text, hunger_fg) = hunger_status(clock.state);
+        let hunger_line = match clock.state {
+            HungerState::Starving => format!("{}, losing health", hunger_text),
+            _ => format!("{} for another {} turns", hunger_text, clock.duration)
+        };
+        lines.push((String::new(), fg));
+        lines.push(("Hunger".to_string(), ctrl_fg));
+        lines.push((hunger_line, hunger_fg));
+    }
+
+    lines.push((String::new(), fg));
+    lines.push(("Status".to_string(), ctrl_fg));
+    let mut effects: Vec<(String, RGB)> = Vec::new();
+    if let Some(confused) = confusion.get(player_entity) {
+        effects.push((format!("Confused for {} turns", confused.turns), return_rgb(CONFUSION_FG)));
+    }
+    if let Some(poison) = poisoned.get(player_entity) {
+        effects.push((format!("Poisoned, {} damage a turn for {} turns", poison.damage, poison.turns), return_rgb(POISON_FG)));
+    }
+    if sneaking.get(player_entity).is_some() { effects.push(("Sneaking".to_string(), return_rgb(SNEAK_FG))); }
+    if effects.is_empty() { effects.push(("Nothing unusual".to_string(), fg)); }
+    lines.append(&mut effects);
+
+    lines.push((String::new(), fg));
+    lines.push(("Progress".to_string(), ctrl_fg));
+    let depth = if map.depth == 0 { "In town".to_string() } else { format!("Depth {}", map.depth) };
+    lines.push((format!("{}, deepest {}", depth, run_stats.deepest_depth), fg));
+    lines.push((format!("Turns taken  {}", run_stats.turns), fg));
+    lines.push((format!("Kills        {}", run_stats.kills), fg));
+
+    let width = lines.iter().map(|(line, _)| line.chars().count() as i32).max().unwrap_or(0) + 4;
+    let panel = Hud::new(ctx).map.place(Anchor::Center, i32::max(width, 30), lines.len() as i32 + 2);
+
+    panel.draw_box(ctx, fg, bg);
+    ctx.print_color(panel.x + 1, panel.y, bg, fg, " Character ");
+    for (i, (line, line_fg)) in lines.iter().enumerate() {
+        ctx.print_color(panel.x + 2, panel.y + 1 + i as i32, *line_fg, bg, line);
+    }
+    ctx.print_color(panel.x + 1, panel.bottom(), ctrl_fg, bg, " ESC ");
+    ctx.print_color(panel.x + 6, panel.bottom(), fg, bg, "to close ");
+
+    if input::action(&gs.ecs, Context::Menu) == Some(Action::Cancel) || input::action(&gs.ecs, Context::Game) == Some(Action::CharacterSheet) {
+        return CharacterSheetResult::Done;
+    }
+    CharacterSheetResult::Showing
+}
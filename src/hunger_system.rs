@@ -1,6 +1,6 @@
 use specs::prelude::*;
 
-use super::{game_log::GameLog, HungerClock, HungerState, RunState, SufferDamage};
+use super::{game_log::{GameLog, LogCategory}, HungerClock, HungerState, RunState, SufferDamage};
 
 pub struct HungerSystem {}
 
@@ -44,27 +44,27 @@ impl<'a> System<'a> for HungerSystem {
                             clock.state = HungerState::Normal;
                             clock.duration = 200;
                             if entity == *player_entity {
-                                log.entries.push("You are no longer well fed.".to_string());
+                                log.push(LogCategory::General, "You are no longer well fed.");
                             }
                         }
                         HungerState::Normal => {
                             clock.state = HungerState::Hungry;
                             clock.duration = 200;
                             if entity == *player_entity {
-                                log.entries.push("You are hungry.".to_string());
+                                log.push(LogCategory::Danger, "You are hungry.");
                             }
                         }
                         HungerState::Hungry => {
                             clock.state = HungerState::Starving;
                             clock.duration = 200;
                             if entity == *player_entity {
-                                log.entries.push("You are starving!".to_string());
+                                log.push(LogCategory::Danger, "You are starving!");
                             }
                         }
                         HungerState::Starving => {
                             // Inflict damage from hunger
                             if entity == *player_entity {
-                                log.entries.push("Your hunger pangs are getting painful!".to_string());
+                                log.push(LogCategory::Danger, "Your hunger pangs are getting painful!");
                             }
                             SufferDamage::new_damage(&mut inflict_damage, entity, 1);
                         }
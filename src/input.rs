@@ -0,0 +1,144 @@
+use gilrs::{Axis, Button, EventType, Gilrs};
+use rltk::{Rltk, VirtualKeyCode};
+use specs::prelude::*;
+
+use super::keymap::{Action, Context, Keymap};
+
+/// How far a stick has to be pushed before it counts as a direction.
+const STICK_THRESHOLD: f32 = 0.5;
+
+/// A gamepad button, with the left stick and the d-pad folded into eight directions.
+#[derive(PartialEq, Copy, Clone)]
+pub enum PadButton {
+    Direction { dx: i32, dy: i32 },
+    South, East, West, North,
+    Start, Select,
+    LeftBumper, RightBumper
+}
+
+impl PadButton {
+    fn action(&self, context: Context) -> Option<Action> {
+        match (context, self) {
+            (Context::Game, PadButton::Direction{ dx, dy }) => Action::ALL.iter().find(|action| action.direction() == Some((*dx, *dy))).copied(),
+            (Context::Game, PadButton::South) => Some(Action::PickUp),
+            (Context::Game, PadButton::East) => Some(Action::Examine),
+            (Context::Game, PadButton::West) => Some(Action::Wait),
+            (Context::Game, PadButton::North) => Some(Action::Inventory),
+            (Context::Game, PadButton::Start) => Some(Action::SaveAndQuit),
+            (Context::Game, PadButton::Select) => Some(Action::Explore),
+            (Context::Game, PadButton::LeftBumper) => Some(Action::TravelToStairs),
+            (Context::Game, PadButton::RightBumper) => Some(Action::Descend),
+
+            (Context::Menu, PadButton::Direction{ dx: _, dy: -1 }) => Some(Action::MenuUp),
+            (Context::Menu, PadButton::Direction{ dx: _, dy: 1 }) => Some(Action::MenuDown),
+            (Context::Menu, PadButton::South) => Some(Action::Confirm),
+            (Context::Menu, PadButton::East) => Some(Action::Cancel),
+            (Context::Menu, PadButton::LeftBumper) |
+            (Context::Menu, PadButton::RightBumper) => Some(Action::SwitchMode),
+            _ => None
+        }
+    }
+}
+
+/// Whatever the player pressed this frame, from the keyboard or a gamepad. Refreshed at
+/// the start of every tick, so menus and the player's turn read input the same way.
+#[derive(Default)]
+pub struct Input {
+    pub key: Option<VirtualKeyCode>,
+    pub shift: bool,
+    pub pad: Option<PadButton>
+}
+
+impl Input {
+    pub fn new() -> Input {
+        Input { key: None, shift: false, pad: None }
+    }
+
+    /// True if anything at all was pressed.
+    pub fn pressed(&self) -> bool {
+        self.key.is_some() || self.pad.is_some()
+    }
+
+    pub fn action(&self, keymap: &Keymap, context: Context) -> Option<Action> {
+        if let Some(button) = self.pad {
+            return button.action(context);
+        }
+        self.key.and_then(|key| keymap.action(context, key, self.shift))
+    }
+}
+
+/// The action pressed this frame in the given context.
+pub fn action(ecs: &World, context: Context) -> Option<Action> {
+    ecs.fetch::<Input>().action(&ecs.fetch::<Keymap>(), context)
+}
+
+/// Reads controller events. Lives on `State` rather than in the ECS, as the
+/// controller handle can't be shared between threads.
+pub struct Gamepad {
+    gilrs: Option<Gilrs>,
+    stick: (i32, i32)
+}
+
+impl Gamepad {
+    /// Works without a controller, or without controller support, by never reporting anything.
+    pub fn new() -> Gamepad {
+        Gamepad { gilrs: Gilrs::new().ok(), stick: (0, 0) }
+    }
+
+    /// Records this frame's key and gamepad presses in the `Input` resource.
+    pub fn update(&mut self, ecs: &mut World, ctx: &Rltk) {
+        let pad = self.poll();
+        let mut input = ecs.write_resource::<Input>();
+        input.key = ctx.key;
+        input.shift = ctx.shift;
+        input.pad = pad;
+    }
+
+    /// The first button pressed since last frame. A stick only counts once each time it
+    /// is pushed into a new direction.
+    fn poll(&mut self) -> Option<PadButton> {
+        let gilrs = self.gilrs.as_mut()?;
+        let mut pressed: Option<PadButton> = None;
+        while let Some(event) = gilrs.next_event() {
+            let button = match event.event {
+                EventType::ButtonPressed(button, _) => match button {
+                    Button::DPadUp => Some(PadButton::Direction{ dx: 0, dy: -1 }),
+                    Button::DPadDown => Some(PadButton::Direction{ dx: 0, dy: 1 }),
+                    Button::DPadLeft => Some(PadButton::Direction{ dx: -1, dy: 0 }),
+                    Button::DPadRight => Some(PadButton::Direction{ dx: 1, dy: 0 }),
+                    Button::South => Some(PadButton::South),
+                    Button::East => Some(PadButton::East),
+                    Button::West => Some(PadButton::West),
+                    Button::North => Some(PadButton::North),
+                    Button::Start => Some(PadButton::Start),
+                    Button::Select => Some(PadButton::Select),
+                    Button::LeftTrigger => Some(PadButton::LeftBumper),
+                    Button::RightTrigger => Some(PadButton::RightBumper),
+                    _ => None
+                },
+                EventType::AxisChanged(Axis::LeftStickX, value, _) => {
+                    let dy = self.stick.1;
+                    stick_moved(&mut self.stick, stick_direction(value), dy)
+                }
+                EventType::AxisChanged(Axis::LeftStickY, value, _) => {
+                    // Pushing the stick up is positive, but up the screen is negative
+                    let dx = self.stick.0;
+                    stick_moved(&mut self.stick, dx, -stick_direction(value))
+                }
+                _ => None
+            };
+            if pressed.is_none() { pressed = button; }
+        }
+        pressed
+    }
+}
+
+fn stick_moved(stick: &mut (i32, i32), dx: i32, dy: i32) -> Option<PadButton> {
+    if (dx, dy) == *stick { return None; }
+    *stick = (dx, dy);
+    if dx == 0 && dy == 0 { None } else { Some(PadButton::Direction{ dx, dy }) }
+}
+
+fn stick_direction(value: f32) -> i32 {
+    if value > STICK_THRESHOLD { 1 } else if value < -STICK_THRESHOLD { -1 } else { 0 }
+}
@@ -2,11 +2,11 @@ use rltk::RGB;
 use specs::prelude::*;
 
 use super::{glyph_index::{AOE_GLYPH, CONFUSION_GLYPH, HEAL_GLYPH, POW_GLYPH},
-            colors::{return_rgb, AOE_FG, DMG_FG, DEFAULT_BG, HEAL_FG, CONFUSION_FG}};
+            colors::{return_rgb, AOE_FG, DMG_FG, DEFAULT_BG, FIRE_LIGHT, HEAL_FG, CONFUSION_FG}};
 
 
-use super::{AreaOfEffect, CombatStats, Confusion, Consumable, Equippable, Equipped, game_log::GameLog, HungerClock, HungerState, InBackpack, InflictsDamage, MagicMapper, Map,
-             Name, particle_system::ParticleBuilder, Position, ProvidesFood, ProvidesHealing, RunState, SufferDamage, WantsToUseItem, WantsToDropItem, WantsToPickupItem, WantsToRemoveItem};
+use super::{AreaOfEffect, CombatStats, Confusion, Consumable, Equippable, Equipped, game_log::{GameLog, LogCategory, LogEntry}, GoldPile, HungerClock, HungerState, InBackpack, InflictsDamage, LightSource, MagicMapper, Map,
+             McGuffin, Name, noise_system::{EXPLOSION_NOISE, NoiseBuilder}, particle_system::ParticleBuilder, Position, ProvidesFood, ProvidesHealing, RunState, SufferDamage, TileType, Wallet, WantsToUseItem, WantsToDropItem, WantsToPickupItem, WantsToRemoveItem};
 
 pub struct ItemCollectionSystem {}
 
@@ -14,21 +14,46 @@ impl<'a> System<'a> for ItemCollectionSystem {
     
     type SystemData = ( ReadExpect<'a, Entity>,
                         WriteExpect<'a, GameLog>,
+                        Entities<'a>,
                         WriteStorage<'a, WantsToPickupItem>,
                         WriteStorage<'a, Position>,
                         ReadStorage<'a, Name>,
-                        WriteStorage<'a, InBackpack>
+                        WriteStorage<'a, InBackpack>,
+                        ReadStorage<'a, GoldPile>,
+                        WriteStorage<'a, Wallet>,
+                        ReadStorage<'a, McGuffin>,
+                        WriteExpect<'a, RunState>
                     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (player_entity, mut gamelog, mut wants_pickup, mut positions, names, mut backpack) = data;
+        let (player_entity, mut gamelog, entities, mut wants_pickup, mut positions, names, mut backpack, gold_piles, mut wallets,
+             mcguffins, mut runstate) = data;
 
         for pickup in wants_pickup.join() {
             positions.remove(pickup.item);
+
+            // Gold goes straight into the wallet rather than the backpack
+            if let Some(gold) = gold_piles.get(pickup.item) {
+                if let Some(wallet) = wallets.get_mut(pickup.collected_by) {
+                    wallet.gold += gold.amount;
+                }
+                if pickup.collected_by == *player_entity {
+                    gamelog.push(LogCategory::Item, format!("You pick up {} gold.", gold.amount));
+                }
+                entities.delete(pickup.item).expect("Unable to delete gold");
+                continue;
+            }
+
+
             backpack.insert(pickup.item, InBackpack{ owner: pickup.collected_by }).expect("Unable to insert backpack entry");
 
             if pickup.collected_by == *player_entity {
-                gamelog.entries.push(format!("You pick up the {}.", names.get(pickup.item).unwrap().name));
+                gamelog.push(LogCategory::Item, format!("You pick up the {}.", names.get(pickup.item).unwrap().name));
+
+                // Quest complete!
+                if mcguffins.get(pickup.item).is_some() {
+                    *runstate = RunState::Victory;
+                }
             }
         }
 
@@ -60,20 +85,22 @@ impl<'a> System<'a> for ItemUseSystem {
                         ReadStorage<'a, ProvidesFood>,
                         WriteStorage<'a, HungerClock>,   
                         ReadStorage<'a, MagicMapper>,
-                        WriteExpect<'a, RunState>           
+                        WriteExpect<'a, RunState>,
+                        WriteExpect<'a, NoiseBuilder>
                        );
     fn run(&mut self, data: Self::SystemData) {
-        let (player_entity, mut gamelog, map, entities, mut wants_use, names,
+        let (player_entity, mut gamelog, mut map, entities, mut wants_use, names,
              consumables, healing, inflict_damage, mut combat_stats,
              mut suffer_damage, aoe, mut confused, equippable, mut equipped,
              mut backpack, mut particle_builder, positions, provides_food,
-             mut hunger_clocks, magic_mapper, mut runstate) = data;
+             mut hunger_clocks, magic_mapper, mut runstate, mut noise) = data;
         
         for (entity, useitem) in (&entities, &wants_use).join() {
             let mut used_item = true;
 
             // Targeting
             let mut targets: Vec<Entity> = Vec::new();
+            let mut weakened_walls: Vec<usize> = Vec::new();
             match useitem.target {
                 None => { targets.push( *player_entity ); }
                 Some(target) => {
@@ -95,16 +122,35 @@ impl<'a> System<'a> for ItemUseSystem {
                                 for mob in map.tile_content[idx].iter() {
                                     targets.push(*mob);
                                 }
+                                if map.wall_damage.contains_key(&idx) && map.is_diggable(tile_idx.x, tile_idx.y) {
+                                    weakened_walls.push(idx);
+                                }
                                 let fg: RGB = return_rgb(AOE_FG);
                                 let bg: RGB = return_rgb(DEFAULT_BG);
                                 let glyph = rltk::to_cp437(AOE_GLYPH);
                                 particle_builder.request(tile_idx.x, tile_idx.y, fg, bg, glyph, 200.0);
                             }
+
+                            // Explosions light up the area around them for a moment
+                            if inflict_damage.get(useitem.item).is_some() {
+                                let light = LightSource{ color: FIRE_LIGHT, range: area_effect.radius + 2 };
+                                particle_builder.request_lit(target.x, target.y, return_rgb(AOE_FG), return_rgb(DEFAULT_BG), rltk::to_cp437(AOE_GLYPH), 400.0, light);
+                                noise.make_noise(target.x, target.y, EXPLOSION_NOISE);
+                            }
                         }
                     }
                 }
             }
 
+            // Explosions bring down walls that have already been weakened
+            if inflict_damage.get(useitem.item).is_some() && !weakened_walls.is_empty() {
+                for idx in weakened_walls.iter() {
+                    map.wall_damage.remove(idx);
+                    map.tiles[*idx] = TileType::Rubble;
+                }
+                gamelog.push(LogCategory::General, "The blast brings down a cracked wall!");
+            }
+
             // If it is equippable, then we want to equip it - and unequip whatever else was in that slot
             let item_equippable = equippable.get(useitem.item);
             match item_equippable {
@@ -119,7 +165,7 @@ impl<'a> System<'a> for ItemUseSystem {
                         if already_equipped.owner == target && already_equipped.slot == target_slot {
                             to_unequip.push(item_entity);
                             if target == *player_entity {
-                                gamelog.entries.push(format!("You unequip {}.", name.name));
+                                gamelog.push(LogCategory::Item, format!("You unequip {}.", name.name));
                             }
                         }
                     }
@@ -133,7 +179,7 @@ impl<'a> System<'a> for ItemUseSystem {
                     equipped.insert(useitem.item, Equipped { owner: target, slot: target_slot }).expect("Unable to insert equipped component");
                     backpack.remove(useitem.item);
                     if target == *player_entity {
-                        gamelog.entries.push(format!("You equip {}.", names.get(useitem.item).unwrap().name));
+                        gamelog.push(LogCategory::Item, format!("You equip {}.", names.get(useitem.item).unwrap().name));
                     }
                 }
             }
@@ -149,7 +195,7 @@ impl<'a> System<'a> for ItemUseSystem {
                     if let Some(hc) = hc {
                         hc.state = HungerState::WellFed;
                         hc.duration = 20;
-                        gamelog.entries.push(format!("You eat the {}.", names.get(useitem.item).unwrap().name));
+                        gamelog.push(LogCategory::Item, format!("You eat the {}.", names.get(useitem.item).unwrap().name));
                     }
                 }
             }
@@ -160,7 +206,7 @@ impl<'a> System<'a> for ItemUseSystem {
                 None => {}
                 Some(_) => {
                     used_item = true;
-                    gamelog.entries.push("The map is revealed to you!".to_string());
+                    gamelog.push(LogCategory::Item, "The map is revealed to you!");
                     *runstate = RunState::MagicMapReveal{ row: 0 };
                 }
             }
@@ -175,7 +221,7 @@ impl<'a> System<'a> for ItemUseSystem {
                         if let Some(stats) = stats {
                             stats.hp = i32::min(stats.max_hp, stats.hp + healer.heal_amount);
                             if entity == *player_entity {
-                                gamelog.entries.push(format!("You drink the {}, healing {} hp.", names.get(useitem.item).unwrap().name, healer.heal_amount));
+                                gamelog.push(LogCategory::Item, format!("You drink the {}, healing {} hp.", names.get(useitem.item).unwrap().name, healer.heal_amount));
                             }
                             used_item = true;
 
@@ -202,7 +248,7 @@ impl<'a> System<'a> for ItemUseSystem {
                         if entity == *player_entity {
                             let mob_name = names.get(*mob).unwrap();
                             let item_name = names.get(useitem.item).unwrap();
-                            gamelog.entries.push(format!("You used {} on {}, inflicting {} hp.", item_name.name, mob_name.name, damage.damage));
+                            gamelog.add(LogEntry::new(LogCategory::Combat).text("You used ").name(&item_name.name).text(" on ").name(&mob_name.name).text(format!(", inflicting {} hp.", damage.damage)));
 
                             let pos = positions.get(*mob);
                             if let Some(pos) = pos {
@@ -230,7 +276,7 @@ impl<'a> System<'a> for ItemUseSystem {
                             if entity == *player_entity {
                                 let mob_name = names.get(*mob).unwrap();
                                 let item_name = names.get(useitem.item).unwrap();
-                                gamelog.entries.push(format!("You use {} on {}, confusing them.", item_name.name, mob_name.name));
+                                gamelog.add(LogEntry::new(LogCategory::Combat).text("You use ").name(&item_name.name).text(" on ").name(&mob_name.name).text(", confusing them."));
                                 
                                 let pos = positions.get(*mob);
                                 if let Some(pos) = pos {
@@ -289,7 +335,7 @@ impl<'a> System<'a> for ItemDropSystem {
             backpack.remove(to_drop.item);
 
             if entity == *player_entity {
-                gamelog.entries.push(format!("You drop the {}.", names.get(to_drop.item).unwrap().name));
+                gamelog.push(LogCategory::Item, format!("You drop the {}.", names.get(to_drop.item).unwrap().name));
             }
         }
 
@@ -317,4 +363,39 @@ impl<'a> System<'a> for ItemRemoveSystem {
 
         wants_remove.clear();
     }
-}
\ No newline at end of file
+}
+
+/// Moves `item` from a vendor's stock into the player's backpack, if the player can pay for it.
+pub fn buy_item(ecs: &mut World, item: Entity, price: i32) {
+    let player_entity = ecs.fetch::<Entity>();
+    let names = ecs.read_storage::<Name>();
+    let mut wallets = ecs.write_storage::<Wallet>();
+    let mut backpack = ecs.write_storage::<InBackpack>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+
+    let item_name = names.get(item).unwrap();
+    if let Some(wallet) = wallets.get_mut(*player_entity) {
+        if wallet.gold < price {
+            gamelog.push(LogCategory::Item, format!("You can't afford the {}.", item_name.name));
+            return;
+        }
+        wallet.gold -= price;
+        backpack.insert(item, InBackpack{ owner: *player_entity }).expect("Unable to insert backpack entry");
+        gamelog.push(LogCategory::Item, format!("You buy the {} for {} gold.", item_name.name, price));
+    }
+}
+
+/// Moves `item` from the player's backpack into the vendor's stock in exchange for gold.
+pub fn sell_item(ecs: &mut World, vendor: Entity, item: Entity, price: i32) {
+    let player_entity = ecs.fetch::<Entity>();
+    let names = ecs.read_storage::<Name>();
+    let mut wallets = ecs.write_storage::<Wallet>();
+    let mut backpack = ecs.write_storage::<InBackpack>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+
+    if let Some(wallet) = wallets.get_mut(*player_entity) {
+        wallet.gold += price;
+        backpack.insert(item, InBackpack{ owner: vendor }).expect("Unable to insert backpack entry");
+        gamelog.push(LogCategory::Item, format!("You sell the {} for {} gold.", names.get(item).unwrap().name, price));
+    }
+}
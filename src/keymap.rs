@@ -0,0 +1,371 @@
+use rltk::VirtualKeyCode;
+use serde::{Serialize, Deserialize};
+use std::fs;
+use std::path::Path;
+
+use super::game_log::{GameLog, LogCategory};
+
+const KEYMAP_FILE: &str = "./keymap.json";
+
+/// Actions that must always keep a key, or a screen could be impossible to leave.
+const ESSENTIAL: [Action; 2] = [Action::Cancel, Action::Confirm];
+
+/// Where an action applies. A key can mean one thing while playing and another in a menu.
+#[derive(PartialEq, Eq, Copy, Clone)]
+pub enum Context { Game, Menu }
+
+/// Everything a key can be bound to.
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum Action {
+    MoveNorth, MoveSouth, MoveEast, MoveWest, MoveNorthEast, MoveNorthWest, MoveSouthEast, MoveSouthWest,
+    Wait, PickUp, Inventory, Drop, Unequip, Search, Disarm, Sneak, Explore, Examine, Descend, TravelToStairs,
+    CharacterSheet, Overview, MessageLog, Keybindings, SaveAndQuit,
+    MenuUp, MenuDown, Confirm, Cancel, SwitchMode
+}
+
+impl Action {
+    pub const ALL: [Action; 30] = [
+        Action::MoveNorth, Action::MoveSouth, Action::MoveEast, Action::MoveWest,
+        Action::MoveNorthEast, Action::MoveNorthWest, Action::MoveSouthEast, Action::MoveSouthWest,
+        Action::Wait, Action::PickUp, Action::Inventory, Action::Drop, Action::Unequip, Action::Search,
+        Action::Disarm, Action::Sneak, Action::Explore, Action::Examine, Action::Descend, Action::TravelToStairs,
+        Action::CharacterSheet, Action::Overview, Action::MessageLog, Action::Keybindings, Action::SaveAndQuit,
+        Action::MenuUp, Action::MenuDown, Action::Confirm, Action::Cancel, Action::SwitchMode
+    ];
+
+    pub fn context(&self) -> Context {
+        match self {
+            Action::MenuUp | Action::MenuDown | Action::Confirm | Action::Cancel | Action::SwitchMode => Context::Menu,
+            _ => Context::Game
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::MoveNorth => "Move north",
+            Action::MoveSouth => "Move south",
+            Action::MoveEast => "Move east",
+            Action::MoveWest => "Move west",
+            Action::MoveNorthEast => "Move north-east",
+            Action::MoveNorthWest => "Move north-west",
+            Action::MoveSouthEast => "Move south-east",
+            Action::MoveSouthWest => "Move south-west",
+            Action::Wait => "Wait a turn",
+            Action::PickUp => "Pick up",
+            Action::Inventory => "Inventory",
+            Action::Drop => "Drop item",
+            Action::Unequip => "Unequip item",
+            Action::Search => "Search",
+            Action::Disarm => "Disarm trap",
+            Action::Sneak => "Toggle sneaking",
+            Action::Explore => "Auto-explore",
+            Action::Examine => "Look around",
+            Action::Descend => "Go down stairs",
+            Action::TravelToStairs => "Travel to stairs",
+            Action::CharacterSheet => "Character sheet",
+            Action::Overview => "Overview map",
+            Action::MessageLog => "Message log",
+            Action::Keybindings => "Keybindings",
+            Action::SaveAndQuit => "Save and quit",
+            Action::MenuUp => "Menu up",
+            Action::MenuDown => "Menu down",
+            Action::Confirm => "Confirm",
+            Action::Cancel => "Cancel",
+            Action::SwitchMode => "Switch buy/sell"
+        }
+    }
+
+    /// The step a movement action takes.
+    pub fn direction(&self) -> Option<(i32, i32)> {
+        match self {
+            Action::MoveNorth => Some((0, -1)),
+            Action::MoveSouth => Some((0, 1)),
+            Action::MoveEast => Some((1, 0)),
+            Action::MoveWest => Some((-1, 0)),
+            Action::MoveNorthEast => Some((1, -1)),
+            Action::MoveNorthWest => Some((-1, -1)),
+            Action::MoveSouthEast => Some((1, 1)),
+            Action::MoveSouthWest => Some((-1, 1)),
+            _ => None
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Copy, Clone)]
+pub struct KeyPress {
+    pub key: VirtualKeyCode,
+    pub shift: bool
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum Preset { Vi, Numpad, Wasd }
+
+/// How a binding is written to the config file. An action the player has cleared is
+/// written with no key, so it stays cleared.
+#[derive(Serialize, Deserialize)]
+struct BindingEntry {
+    #[serde(default)]
+    key: Option<String>,
+    shift: bool,
+    action: Action
+}
+
+pub struct Keymap {
+    bindings: Vec<(KeyPress, Action)>
+}
+
+impl Keymap {
+    /// The vi preset with the config file's bindings laid over it. Actions the file doesn't
+    /// mention, such as ones added since it was written, keep their vi keys.
+    pub fn load() -> Keymap {
+        if !Path::new(KEYMAP_FILE).exists() { return Keymap::preset(Preset::Vi); }
+        fs::read_to_string(KEYMAP_FILE).ok()
+            .and_then(|data| Keymap::from_json(&data))
+            .unwrap_or_else(|| Keymap::preset(Preset::Vi))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn save(&self, _log: &mut GameLog) {
+    }
+
+    /// Writes the bindings to the config file, noting in the log if that fails.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, log: &mut GameLog) {
+        if let Err(error) = fs::write(KEYMAP_FILE, self.to_json()) {
+            log.push(LogCategory::Danger, format!("Your keybindings couldn't be saved: {}", error));
+        }
+    }
+
+    fn to_json(&self) -> String {
+        let mut entries: Vec<BindingEntry> = self.bindings.iter()
+            .filter_map(|(press, action)| key_name(press.key).map(|name| BindingEntry{ key: Some(name.to_string()), shift: press.shift, action: *action }))
+            .collect();
+        for action in Action::ALL.iter() {
+            if !self.bindings.iter().any(|(_, bound_action)| bound_action == action) {
+                entries.push(BindingEntry{ key: None, shift: false, action: *action });
+            }
+        }
+        serde_json::to_string_pretty(&entries).expect("Unable to serialize keymap")
+    }
+
+    fn from_json(data: &str) -> Option<Keymap> {
+        let entries: Vec<BindingEntry> = serde_json::from_str(data).ok()?;
+
+        let mut keymap = Keymap::preset(Preset::Vi);
+        keymap.bindings.retain(|(_, action)| !entries.iter().any(|entry| entry.action == *action));
+        for entry in entries.iter() {
+            if let Some(key) = entry.key.as_ref().and_then(|name| key_from_name(name)) {
+                keymap.set(KeyPress{ key, shift: entry.shift }, entry.action);
+            }
+        }
+
+        // A file that leaves a screen impossible to leave gets the vi keys back for it
+        let vi = Keymap::preset(Preset::Vi);
+        for essential in ESSENTIAL.iter() {
+            if !keymap.bindings.iter().any(|(_, action)| action == essential) {
+                for (press, action) in vi.bindings.iter().filter(|(_, action)| action == essential) {
+                    keymap.set(*press, *action);
+                }
+            }
+        }
+        Some(keymap)
+    }
+
+    pub fn preset(preset: Preset) -> Keymap {
+        use VirtualKeyCode::*;
+
+        let mut keymap = Keymap { bindings: Vec::new() };
+        let mut bind = |key: VirtualKeyCode, shift: bool, action: Action| keymap.set(KeyPress{ key, shift }, action);
+
+        // Menus and the arrow keys work the same everywhere
+        bind(Up, false, Action::MenuUp);
+        bind(Down, false, Action::MenuDown);
+        bind(Numpad8, false, Action::MenuUp);
+        bind(Numpad2, false, Action::MenuDown);
+        bind(Return, false, Action::Confirm);
+        bind(NumpadEnter, false, Action::Confirm);
+        bind(Escape, false, Action::Cancel);
+        bind(Tab, false, Action::SwitchMode);
+
+        bind(Up, false, Action::MoveNorth);
+        bind(Down, false, Action::MoveSouth);
+        bind(Right, false, Action::MoveEast);
+        bind(Left, false, Action::MoveWest);
+        bind(Space, false, Action::Wait);
+        bind(I, false, Action::Inventory);
+        bind(R, false, Action::Unequip);
+        bind(T, false, Action::Disarm);
+        bind(O, false, Action::Explore);
+        bind(Period, false, Action::Descend);
+        bind(Period, true, Action::TravelToStairs);
+        bind(C, false, Action::CharacterSheet);
+        bind(Tab, false, Action::Overview);
+        bind(M, false, Action::MessageLog);
+        bind(Slash, true, Action::Keybindings);
+        bind(Escape, false, Action::SaveAndQuit);
+
+        if preset != Preset::Wasd {
+            bind(Numpad8, false, Action::MoveNorth);
+            bind(Numpad2, false, Action::MoveSouth);
+            bind(Numpad6, false, Action::MoveEast);
+            bind(Numpad4, false, Action::MoveWest);
+            bind(Numpad9, false, Action::MoveNorthEast);
+            bind(Numpad7, false, Action::MoveNorthWest);
+            bind(Numpad3, false, Action::MoveSouthEast);
+            bind(Numpad1, false, Action::MoveSouthWest);
+            bind(Numpad5, false, Action::Wait);
+            bind(G, false, Action::PickUp);
+            bind(D, false, Action::Drop);
+            bind(S, false, Action::Search);
+            bind(Z, false, Action::Sneak);
+            bind(X, false, Action::Examine);
+        }
+
+        match preset {
+            Preset::Vi => {
+                bind(K, false, Action::MoveNorth);
+                bind(J, false, Action::MoveSouth);
+                bind(L, false, Action::MoveEast);
+                bind(H, false, Action::MoveWest);
+                bind(U, false, Action::MoveNorthEast);
+                bind(Y, false, Action::MoveNorthWest);
+                bind(N, false, Action::MoveSouthEast);
+                bind(B, false, Action::MoveSouthWest);
+            }
+            Preset::Numpad => {}
+            Preset::Wasd => {
+                bind(W, false, Action::MoveNorth);
+                bind(S, false, Action::MoveSouth);
+                bind(D, false, Action::MoveEast);
+                bind(A, false, Action::MoveWest);
+                bind(E, false, Action::MoveNorthEast);
+                bind(Q, false, Action::MoveNorthWest);
+                bind(C, false, Action::MoveSouthEast);
+                // C moves here, and shift runs, so the character sheet moves to P
+                bind(P, false, Action::CharacterSheet);
+                bind(Z, false, Action::MoveSouthWest);
+                bind(G, false, Action::PickUp);
+                bind(G, true, Action::Drop);
+                bind(F, false, Action::Search);
+                bind(V, false, Action::Sneak);
+                bind(L, false, Action::Examine);
+            }
+        }
+
+        keymap
+    }
+
+    /// What a key press means in the given context. A shifted key that isn't bound by
+    /// itself falls back to the unshifted binding.
+    pub fn action(&self, context: Context, key: VirtualKeyCode, shift: bool) -> Option<Action> {
+        let find = |shift: bool| self.bindings.iter()
+            .find(|(press, action)| press.key == key && press.shift == shift && action.context() == context)
+            .map(|(_, action)| *action);
+        find(shift).or_else(|| if shift { find(false) } else { None })
+    }
+
+    /// Binds a key to an action, taking it away from whatever it did before in that context.
+    /// Refuses, returning false, if that would leave Cancel or Confirm without a key.
+    pub fn bind(&mut self, press: KeyPress, action: Action) -> bool {
+        if self.strands_essential(|bound, bound_action| *bound == press && bound_action != action && bound_action.context() == action.context()) {
+            return false;
+        }
+        self.set(press, action);
+        true
+    }
+
+    /// Clears every key from an action, unless it is Cancel or Confirm. Returns false if it refused.
+    pub fn unbind_action(&mut self, action: Action) -> bool {
+        if self.strands_essential(|_, bound_action| bound_action == action) { return false; }
+        self.bindings.retain(|(_, bound_action)| *bound_action != action);
+        true
+    }
+
+    fn set(&mut self, press: KeyPress, action: Action) {
+        self.bindings.retain(|(bound, bound_action)| !(*bound == press && bound_action.context() == action.context()));
+        self.bindings.push((press, action));
+    }
+
+    /// True if taking away the bindings `removed` picks out would leave an essential action with no key.
+    fn strands_essential(&self, removed: impl Fn(&KeyPress, Action) -> bool) -> bool {
+        ESSENTIAL.iter().any(|essential| {
+            self.bindings.iter().any(|(_, action)| action == essential)
+                && !self.bindings.iter().any(|(press, action)| action == essential && !removed(press, *action))
+        })
+    }
+
+    /// The keys bound to an action, written out for display.
+    pub fn describe(&self, action: Action) -> String {
+        let keys: Vec<String> = self.bindings.iter()
+            .filter(|(_, bound_action)| *bound_action == action)
+            .filter_map(|(press, _)| key_name(press.key).map(|name| if press.shift { format!("Shift+{}", name) } else { name.to_string() }))
+            .collect();
+        if keys.is_empty() { "-".to_string() } else { keys.join(", ") }
+    }
+}
+
+const KEY_NAMES: [(VirtualKeyCode, &str); 77] = [
+    (VirtualKeyCode::A, "A"), (VirtualKeyCode::B, "B"), (VirtualKeyCode::C, "C"), (VirtualKeyCode::D, "D"),
+    (VirtualKeyCode::E, "E"), (VirtualKeyCode::F, "F"), (VirtualKeyCode::G, "G"), (VirtualKeyCode::H, "H"),
+    (VirtualKeyCode::I, "I"), (VirtualKeyCode::J, "J"), (VirtualKeyCode::K, "K"), (VirtualKeyCode::L, "L"),
+    (VirtualKeyCode::M, "M"), (VirtualKeyCode::N, "N"), (VirtualKeyCode::O, "O"), (VirtualKeyCode::P, "P"),
+    (VirtualKeyCode::Q, "Q"), (VirtualKeyCode::R, "R"), (VirtualKeyCode::S, "S"), (VirtualKeyCode::T, "T"),
+    (VirtualKeyCode::U, "U"), (VirtualKeyCode::V, "V"), (VirtualKeyCode::W, "W"), (VirtualKeyCode::X, "X"),
+    (VirtualKeyCode::Y, "Y"), (VirtualKeyCode::Z, "Z"),
+    (VirtualKeyCode::Key0, "0"), (VirtualKeyCode::Key1, "1"), (VirtualKeyCode::Key2, "2"), (VirtualKeyCode::Key3, "3"),
+    (VirtualKeyCode::Key4, "4"), (VirtualKeyCode::Key5, "5"), (VirtualKeyCode::Key6, "6"), (VirtualKeyCode::Key7, "7"),
+    (VirtualKeyCode::Key8, "8"), (VirtualKeyCode::Key9, "9"),
+    (VirtualKeyCode::Numpad0, "Numpad0"), (VirtualKeyCode::Numpad1, "Numpad1"), (VirtualKeyCode::Numpad2, "Numpad2"),
+    (VirtualKeyCode::Numpad3, "Numpad3"), (VirtualKeyCode::Numpad4, "Numpad4"), (VirtualKeyCode::Numpad5, "Numpad5"),
+    (VirtualKeyCode::Numpad6, "Numpad6"), (VirtualKeyCode::Numpad7, "Numpad7"), (VirtualKeyCode::Numpad8, "Numpad8"),
+    (VirtualKeyCode::Numpad9, "Numpad9"), (VirtualKeyCode::NumpadEnter, "NumpadEnter"),
+    (VirtualKeyCode::Up, "Up"), (VirtualKeyCode::Down, "Down"), (VirtualKeyCode::Left, "Left"), (VirtualKeyCode::Right, "Right"),
+    (VirtualKeyCode::Home, "Home"), (VirtualKeyCode::End, "End"), (VirtualKeyCode::PageUp, "PageUp"), (VirtualKeyCode::PageDown, "PageDown"),
+    (VirtualKeyCode::Insert, "Insert"), (VirtualKeyCode::Delete, "Delete"),
+    (VirtualKeyCode::F1, "F1"), (VirtualKeyCode::F2, "F2"), (VirtualKeyCode::F3, "F3"), (VirtualKeyCode::F4, "F4"),
+    (VirtualKeyCode::F5, "F5"), (VirtualKeyCode::F6, "F6"), (VirtualKeyCode::F7, "F7"), (VirtualKeyCode::F8, "F8"),
+    (VirtualKeyCode::Escape, "Escape"), (VirtualKeyCode::Return, "Enter"), (VirtualKeyCode::Space, "Space"),
+    (VirtualKeyCode::Tab, "Tab"), (VirtualKeyCode::Back, "Backspace"),
+    (VirtualKeyCode::Period, "."), (VirtualKeyCode::Comma, ","), (VirtualKeyCode::Slash, "/"),
+    (VirtualKeyCode::Semicolon, ";"), (VirtualKeyCode::Apostrophe, "'"), (VirtualKeyCode::Minus, "-"),
+    (VirtualKeyCode::Equals, "=")
+];
+
+pub fn key_name(key: VirtualKeyCode) -> Option<&'static str> {
+    KEY_NAMES.iter().find(|(known, _)| *known == key).map(|(_, name)| *name)
+}
+
+fn key_from_name(name: &str) -> Option<VirtualKeyCode> {
+    KEY_NAMES.iter().find(|(_, known)| *known == name).map(|(key, _)| *key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cleared_and_rebound_actions_survive_save_and_load() {
+        let mut keymap = Keymap::preset(Preset::Vi);
+        assert!(keymap.unbind_action(Action::Inventory));
+        assert!(keymap.bind(KeyPress{ key: VirtualKeyCode::X, shift: false }, Action::Wait));
+
+        let loaded = Keymap::from_json(&keymap.to_json()).expect("Saved keymap should load");
+        assert_eq!(loaded.describe(Action::Inventory), "-");
+        assert_eq!(loaded.action(Context::Game, VirtualKeyCode::X, false), Some(Action::Wait));
+        for action in Action::ALL.iter() {
+            assert_eq!(loaded.describe(*action), keymap.describe(*action));
+        }
+    }
+
+    #[test]
+    fn cancel_and_confirm_keep_a_key() {
+        let mut keymap = Keymap::preset(Preset::Vi);
+        assert!(!keymap.unbind_action(Action::Cancel));
+        assert!(!keymap.unbind_action(Action::Confirm));
+        assert_ne!(keymap.describe(Action::Cancel), "-");
+
+        // Escape is Cancel's only key, so it can't be handed to another menu action
+        assert!(!keymap.bind(KeyPress{ key: VirtualKeyCode::Escape, shift: false }, Action::MenuUp));
+        assert_eq!(keymap.action(Context::Menu, VirtualKeyCode::Escape, false), Some(Action::Cancel));
+    }
+}
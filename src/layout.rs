@@ -0,0 +1,146 @@
+use rltk::{Rect, RGB, Rltk};
+
+/// Rows at the bottom of the screen kept for the status, log and mini-map panels.
+const HUD_HEIGHT: i32 = 10;
+
+/// Columns taken by the status panel on the left of the HUD.
+const STATUS_WIDTH: i32 = 19;
+
+/// Columns taken by the mini-map on the right of the HUD.
+const MINIMAP_WIDTH: i32 = 22;
+
+/// Which corner or side of a panel another is placed against.
+#[derive(PartialEq, Copy, Clone)]
+pub enum Anchor { TopLeft, TopRight, Center }
+
+/// A rectangle of the console, in cells. A box drawn round a panel sits on its outermost
+/// cells, so panels split side by side share the column between them.
+#[derive(PartialEq, Copy, Clone)]
+pub struct Panel {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32
+}
+
+impl Panel {
+    pub fn new(x: i32, y: i32, width: i32, height: i32) -> Panel {
+        Panel { x, y, width, height }
+    }
+
+    /// The whole console.
+    pub fn screen(ctx: &Rltk) -> Panel {
+        let (width, height) = ctx.get_char_size();
+        Panel::new(0, 0, width as i32, height as i32)
+    }
+
+    pub fn right(&self) -> i32 {
+        self.x + self.width - 1
+    }
+
+    pub fn bottom(&self) -> i32 {
+        self.y + self.height - 1
+    }
+
+    /// The space inside the panel's border.
+    pub fn inner(&self) -> Panel {
+        Panel::new(self.x + 1, self.y + 1, self.width - 2, self.height - 2)
+    }
+
+    /// Cuts `rows` off the bottom, returning what is left above and the strip cut off.
+    pub fn split_bottom(&self, rows: i32) -> (Panel, Panel) {
+        let rows = i32::min(rows, self.height);
+        (Panel::new(self.x, self.y, self.width, self.height - rows),
+         Panel::new(self.x, self.y + self.height - rows, self.width, rows))
+    }
+
+    /// Cuts `columns` off the left, returning the strip cut off and what is left. The two
+    /// share a border column.
+    pub fn split_left(&self, columns: i32) -> (Panel, Panel) {
+        let columns = i32::max(1, i32::min(columns, self.width));
+        (Panel::new(self.x, self.y, columns, self.height),
+         Panel::new(self.x + columns - 1, self.y, self.width - columns + 1, self.height))
+    }
+
+    /// Cuts `columns` off the right, returning what is left and the strip cut off. The two
+    /// share a border column.
+    pub fn split_right(&self, columns: i32) -> (Panel, Panel) {
+        self.split_left(self.width - columns + 1)
+    }
+
+    /// A `width` by `height` panel against `anchor` inside this one, shrunk to fit if needed.
+    pub fn place(&self, anchor: Anchor, width: i32, height: i32) -> Panel {
+        let (width, height) = (i32::min(width, self.width), i32::min(height, self.height));
+        let x = match anchor {
+            Anchor::TopLeft => self.x,
+            Anchor::TopRight => self.right() - width + 1,
+            Anchor::Center => self.x + (self.width - width) / 2
+        };
+        let y = match anchor {
+            Anchor::TopLeft | Anchor::TopRight => self.y,
+            Anchor::Center => self.y + (self.height - height) / 2
+        };
+        Panel::new(x, y, width, height)
+    }
+
+    pub fn rect(&self) -> Rect {
+        Rect::with_size(self.x, self.y, self.width, self.height)
+    }
+
+    /// Clears the panel and draws a border round its edge.
+    pub fn draw_box(&self, ctx: &mut Rltk, fg: RGB, bg: RGB) {
+        ctx.fill_region(self.rect(), rltk::to_cp437(' '), fg, bg);
+        ctx.draw_hollow_box(self.x, self.y, self.width - 1, self.height - 1, fg, bg);
+    }
+
+    /// Sets a title into the top border, between a pair of ┤ ├ caps.
+    pub fn title(&self, ctx: &mut Rltk, fg: RGB, bg: RGB, title: &str) {
+        ctx.set(self.x + 1, self.y, fg, bg, rltk::to_cp437('┤'));
+        ctx.print_color(self.x + 2, self.y, fg, bg, title);
+        ctx.set(self.x + 2 + title.chars().count() as i32, self.y, fg, bg, rltk::to_cp437('├'));
+    }
+}
+
+/// Where each part of the main game screen goes for the current console size: the map on
+/// top, and the status, log and mini-map panels along the bottom.
+#[derive(PartialEq, Copy, Clone)]
+pub struct Hud {
+    pub map: Panel,
+    pub status: Panel,
+    pub log: Panel,
+    pub minimap: Panel
+}
+
+impl Hud {
+    pub fn new(ctx: &Rltk) -> Hud {
+        let (map, bottom) = Panel::screen(ctx).split_bottom(HUD_HEIGHT);
+        let (status, rest) = bottom.split_left(STATUS_WIDTH);
+        let (log, minimap) = rest.split_right(MINIMAP_WIDTH);
+        Hud { map, status, log, minimap }
+    }
+}
+
+/// Splits a string into lines no longer than `width`, breaking on spaces. A word too long
+/// for a line of its own is cut wherever the line runs out.
+pub fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let width = usize::max(1, width);
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        let line_length = line.chars().count();
+        if !line.is_empty() && line_length + word.len() + 1 > width {
+            lines.push(line);
+            line = String::new();
+        }
+        while word.len() > width {
+            let rest = word.split_off(width);
+            lines.push(word.into_iter().collect());
+            word = rest;
+        }
+        if !line.is_empty() { line.push(' '); }
+        line.extend(word);
+    }
+    if !line.is_empty() { lines.push(line); }
+    lines
+}
@@ -0,0 +1,48 @@
+use rltk::{field_of_view, Point};
+use specs::prelude::*;
+
+use super::{LightSource, Map, Position};
+
+/// Recomputes how much light falls on every tile. Runs before the `VisibilitySystem`,
+/// which hides anything standing in the dark.
+pub struct LightingSystem {}
+
+impl<'a> System<'a> for LightingSystem {
+    type SystemData = ( WriteExpect<'a, Map>,
+                        ReadStorage<'a, Position>,
+                        ReadStorage<'a, LightSource>
+                      );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut map, positions, lights) = data;
+        let tile_count = map.tiles.len();
+
+        // The sun is out on the surface
+        if map.outdoors {
+            map.light = vec![(1.0, 1.0, 1.0); tile_count];
+            return;
+        }
+
+        // A little light filters down to the shallow levels; deeper down it is pitch black
+        let ambient = f32::max(0.0, 0.5 - map.depth as f32 * 0.1);
+        map.light = vec![(ambient, ambient, ambient); tile_count];
+
+        for (pos, light) in (&positions, &lights).join() {
+            let origin = Point::new(pos.x, pos.y);
+            let lit_tiles = field_of_view(origin, light.range, &*map);
+            for tile in lit_tiles.iter() {
+                if tile.x < 0 || tile.x >= map.width || tile.y < 0 || tile.y >= map.height { continue; }
+                let idx = map.xy_idx(tile.x, tile.y);
+                let distance = rltk::DistanceAlg::Pythagoras.distance2d(origin, *tile);
+                let intensity = f32::max(0.0, 1.0 - distance / (light.range as f32 + 1.0));
+
+                let (r, g, b) = map.light[idx];
+                map.light[idx] = (
+                    f32::min(1.0, r + light.color.0 * intensity),
+                    f32::min(1.0, g + light.color.1 * intensity),
+                    f32::min(1.0, b + light.color.2 * intensity)
+                );
+            }
+        }
+    }
+}
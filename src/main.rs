@@ -4,6 +4,9 @@ use rltk::{GameState, Point, Rltk, RGB};
 use specs::prelude::*;
 use specs::saveload::{ SimpleMarker, SimpleMarkerAllocator};
 
+mod auto_action;
+mod boss_system;
+mod camera;
 mod colors;
 use colors::*;
 mod damage_system;
@@ -11,10 +14,15 @@ use damage_system::DamageSystem;
 mod components;
 pub use components::*;
 mod game_log;
+use game_log::{GameLog, LogCategory};
 mod glyph_index;
 mod gui;
 mod hunger_system;
+mod input;
 mod inventory_system;
+mod keymap;
+mod layout;
+mod lighting_system;
 use inventory_system::{ ItemCollectionSystem, ItemUseSystem, ItemDropSystem, ItemRemoveSystem };
 mod map;
 pub use map::*;
@@ -24,16 +32,29 @@ mod melee_combat_system;
 use melee_combat_system::MeleeCombatSystem;
 mod monster_ai_system;
 use monster_ai_system::MonsterAI;
+mod noise_system;
+mod npc_ai_system;
+use npc_ai_system::NpcAI;
+mod overview;
 mod particle_system;
+mod pathfinding;
 mod player;
 use player::*;
 pub mod random_tables;
 mod rect;
 mod rex_assets;
+mod run_stats;
 mod saveload_system;
+mod scent_system;
+mod settings;
 mod spawner;
-use spawner::{player, spawn_room};
+use spawner::player;
+mod status_system;
+mod targeting;
+mod terrain;
+mod terrain_system;
 mod trigger_system;
+mod vaults;
 mod visibility_system;
 use visibility_system::VisibilitySystem;
 
@@ -43,28 +64,46 @@ pub enum RunState {
     PreRun,
     PlayerTurn,
     MonsterTurn,
-    ShowInventory,
-    ShowDropItem,
-    ShowTargeting { range: i32, item: Entity},
+    ShowInventory { selection: usize },
+    ShowDropItem { selection: usize },
+    ShowTargeting { range: i32, item: Entity, target: Option<(i32, i32)> },
     MainMenu {  menu_selection: gui::MainMenuSelection },
     SaveGame,
     NextLevel,
-    ShowRemoveItem,
+    ShowRemoveItem { selection: usize },
     GameOver,
-    MagicMapReveal { row: i32 }
+    MagicMapReveal { row: i32 },
+    ShowExamine { x: i32, y: i32 },
+    ShowVendor { vendor: Entity, mode: gui::VendorMode, selection: usize },
+    ShowKeybindings { selection: usize, waiting: bool },
+    ShowLog { offset: usize, filter: Option<LogCategory> },
+    ShowOverview,
+    ShowCharacter,
+    Victory
  }
 
 
 pub struct State {
-    pub ecs: World
+    pub ecs: World,
+    gamepad: input::Gamepad
 }
 
 impl State {
     fn run_systems(&mut self) {
+        let mut lighting = lighting_system::LightingSystem{};
+        lighting.run_now(&self.ecs);
         let mut vis = VisibilitySystem{};
         vis.run_now(&self.ecs);
+        let mut scent = scent_system::ScentSystem{};
+        scent.run_now(&self.ecs);
         let mut mob = MonsterAI{};
         mob.run_now(&self.ecs);
+        let mut npcs = NpcAI{};
+        npcs.run_now(&self.ecs);
+        let mut bosses = boss_system::BossSystem{};
+        bosses.run_now(&self.ecs);
+        let mut terrain_effects = terrain_system::TerrainSystem{};
+        terrain_effects.run_now(&self.ecs);
         let mut triggers = trigger_system::TriggerSystem{};
         triggers.run_now(&self.ecs);
         let mut mapindex = MapIndexingSystem{};
@@ -83,6 +122,10 @@ impl State {
         item_remove.run_now(&self.ecs);
         let mut hunger = hunger_system::HungerSystem{};
         hunger.run_now(&self.ecs);
+        let mut statuses = status_system::StatusSystem{};
+        statuses.run_now(&self.ecs);
+        let mut noise = noise_system::NoiseSystem{};
+        noise.run_now(&self.ecs);
         let mut particles = particle_system::ParticleSpawnSystem{};
         particles.run_now(&self.ecs);
         
@@ -92,6 +135,7 @@ impl State {
 
 impl GameState for State {
     fn tick(&mut self, ctx: &mut Rltk) {
+        self.gamepad.update(&mut self.ecs, ctx);
 
         let mut newrunstate;
         
@@ -106,25 +150,8 @@ impl GameState for State {
         match newrunstate {
             RunState::MainMenu {..} => {}
             _ => {
-                draw_map(&self.ecs, ctx);
-
-                {
-                    let positions = self.ecs.read_storage::<Position>();
-                    let renderables = self.ecs.read_storage::<Renderable>();
-                    let hidden = self.ecs.read_storage::<Hidden>();
-                    let map = self.ecs.fetch::<Map>();
-
-                    let mut data = (&positions, &renderables, !&hidden).join().collect::<Vec<_>>();
-                    data.sort_by(|&a, &b| b.1.render_order.cmp(&a.1.render_order) );
-
-                    for (pos, render, _hidden) in data.iter() {
-                        let idx = map.xy_idx(pos.x, pos.y);
-                        if map.visible_tiles[idx] { 
-                            ctx.set(pos.x, pos.y, render.fg, render.bg, render.glyph); }
-                    }
-        
-                    gui::draw_ui(&self.ecs, ctx);
-                }
+                camera::render_camera(&self.ecs, ctx);
+                gui::draw_ui(&self.ecs, ctx);
             }
         }
  
@@ -139,10 +166,18 @@ impl GameState for State {
                 newrunstate = player_input(self, ctx);
             }
             RunState::PlayerTurn => {
+                let turns = {
+                    let mut run_stats = self.ecs.write_resource::<run_stats::RunStats>();
+                    run_stats.turns += 1;
+                    run_stats.turns
+                };
+                self.ecs.write_resource::<GameLog>().turn = turns;
                 self.run_systems();
                 self.ecs.maintain();
                 match *self.ecs.fetch::<RunState>() {
                     RunState::MagicMapReveal{ .. } => newrunstate = RunState::MagicMapReveal { row: 0 },
+                    RunState::Victory => newrunstate = RunState::Victory,
+                    RunState::NextLevel => newrunstate = RunState::NextLevel,
                     _ => newrunstate = RunState::MonsterTurn
                 }
             }
@@ -151,17 +186,18 @@ impl GameState for State {
                 self.ecs.maintain();
                 newrunstate = RunState::AwaitingInput;
             }
-            RunState::ShowInventory => {
-                let result = gui::show_drop_use_inventory(self, ctx, "use");
+            RunState::ShowInventory { selection } => {
+                let result = gui::show_drop_use_inventory(self, ctx, "use", selection);
                 match result.0 {
                     gui::ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
                     gui::ItemMenuResult::NoResponse => {}
+                    gui::ItemMenuResult::Highlight { selection } => newrunstate = RunState::ShowInventory { selection },
                     gui::ItemMenuResult::Selected => {
                         let item_entity = result.1.unwrap();
                         let is_ranged = self.ecs.read_storage::<Ranged>();
                         let is_item_ranged = is_ranged.get(item_entity);
                         if let Some(is_item_ranged) = is_item_ranged{
-                            newrunstate = RunState::ShowTargeting { range: is_item_ranged.range, item: item_entity };
+                            newrunstate = RunState::ShowTargeting { range: is_item_ranged.range, item: item_entity, target: None };
                         } else {
                             let mut intent = self.ecs.write_storage::<WantsToUseItem>();
                             intent.insert(*self.ecs.fetch::<Entity>(), WantsToUseItem { item: item_entity, target: None }).expect("Unable to insert intent");
@@ -170,11 +206,12 @@ impl GameState for State {
                     }
                 }
             }
-            RunState::ShowDropItem => {
-                let result = gui::show_drop_use_inventory(self, ctx, "drop");
+            RunState::ShowDropItem { selection } => {
+                let result = gui::show_drop_use_inventory(self, ctx, "drop", selection);
                 match result.0 {
                     gui::ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
                     gui::ItemMenuResult::NoResponse => {}
+                    gui::ItemMenuResult::Highlight { selection } => newrunstate = RunState::ShowDropItem { selection },
                     gui::ItemMenuResult::Selected => {
                         let item_entity = result.1.unwrap();
                         let mut intent = self.ecs.write_storage::<WantsToDropItem>();
@@ -183,14 +220,14 @@ impl GameState for State {
                     }
                 }
             }
-            RunState::ShowTargeting { range, item } => {
-                let result = gui::ranged_target(self, ctx, range);
-                match result.0 {
-                    gui::ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
-                    gui::ItemMenuResult::NoResponse => {}
-                    gui::ItemMenuResult::Selected => {
+            RunState::ShowTargeting { range, item, target } => {
+                let result = gui::ranged_target(self, ctx, range, item, target);
+                match result {
+                    gui::TargetingResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::TargetingResult::Aiming { target } => newrunstate = RunState::ShowTargeting { range, item, target },
+                    gui::TargetingResult::Fire { x, y } => {
                         let mut intent = self.ecs.write_storage::<WantsToUseItem>();
-                        intent.insert(*self.ecs.fetch::<Entity>(), WantsToUseItem { item, target: result.1 }).expect("Unable to insert intent");
+                        intent.insert(*self.ecs.fetch::<Entity>(), WantsToUseItem { item, target: Some(Point::new(x, y)) }).expect("Unable to insert intent");
                         newrunstate = RunState::PlayerTurn;
                     }
                 }
@@ -222,11 +259,12 @@ impl GameState for State {
                 self.goto_next_level();
                 newrunstate = RunState::PreRun;
             }
-            RunState::ShowRemoveItem => {
-                let result = gui::remove_item_menu(self, ctx);
+            RunState::ShowRemoveItem { selection } => {
+                let result = gui::remove_item_menu(self, ctx, selection);
                 match result.0 {
                     gui::ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
                     gui::ItemMenuResult::NoResponse => {}
+                    gui::ItemMenuResult::Highlight { selection } => newrunstate = RunState::ShowRemoveItem { selection },
                     gui::ItemMenuResult::Selected => {
                         let item_entity = result.1.unwrap();
                         let mut intent = self.ecs.write_storage::<WantsToRemoveItem>();
@@ -236,7 +274,68 @@ impl GameState for State {
                 }
             }
             RunState::GameOver => {
-                let result = gui::game_over(ctx);
+                let result = gui::game_over(&self.ecs, ctx);
+                match result {
+                    gui::GameOverResult::NoSelection => {}
+                    gui::GameOverResult::QuitToMenu => {
+                        self.game_over_cleanup();
+                        newrunstate = RunState::MainMenu { menu_selection: gui::MainMenuSelection::NewGame };
+                    }
+                }
+            }
+            RunState::ShowExamine { x, y } => {
+                let result = gui::examine(self, ctx, x, y);
+                match result {
+                    gui::ExamineResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::ExamineResult::Looking { x, y } => newrunstate = RunState::ShowExamine { x, y }
+                }
+            }
+            RunState::ShowKeybindings { selection, waiting } => {
+                let result = gui::keybindings(self, ctx, selection, waiting);
+                match result {
+                    gui::KeybindingsResult::Done => newrunstate = RunState::AwaitingInput,
+                    gui::KeybindingsResult::Showing { selection, waiting } => newrunstate = RunState::ShowKeybindings { selection, waiting }
+                }
+            }
+            RunState::ShowOverview => {
+                let result = gui::overview_map(self, ctx);
+                match result {
+                    gui::OverviewResult::Done => newrunstate = RunState::AwaitingInput,
+                    gui::OverviewResult::Showing => {}
+                }
+            }
+            RunState::ShowCharacter => {
+                let result = gui::character_sheet(self, ctx);
+                match result {
+                    gui::CharacterSheetResult::Done => newrunstate = RunState::AwaitingInput,
+                    gui::CharacterSheetResult::Showing => {}
+                }
+            }
+            RunState::ShowLog { offset, filter } => {
+                let result = gui::message_log(self, ctx, offset, filter);
+                match result {
+                    gui::MessageLogResult::Done => newrunstate = RunState::AwaitingInput,
+                    gui::MessageLogResult::Showing { offset, filter } => newrunstate = RunState::ShowLog { offset, filter }
+                }
+            }
+            RunState::ShowVendor { vendor, mode, selection } => {
+                let result = gui::show_vendor(self, ctx, vendor, mode, selection);
+                match result.0 {
+                    gui::VendorResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::VendorResult::NoResponse => {}
+                    gui::VendorResult::Highlight { selection } => newrunstate = RunState::ShowVendor { vendor, mode, selection },
+                    gui::VendorResult::SwitchMode => {
+                        match mode {
+                            gui::VendorMode::Buy => newrunstate = RunState::ShowVendor { vendor, mode: gui::VendorMode::Sell, selection: 0 },
+                            gui::VendorMode::Sell => newrunstate = RunState::ShowVendor { vendor, mode: gui::VendorMode::Buy, selection: 0 }
+                        }
+                    }
+                    gui::VendorResult::Buy => inventory_system::buy_item(&mut self.ecs, result.1.unwrap(), result.2),
+                    gui::VendorResult::Sell => inventory_system::sell_item(&mut self.ecs, vendor, result.1.unwrap(), result.2)
+                }
+            }
+            RunState::Victory => {
+                let result = gui::victory(&self.ecs, ctx);
                 match result {
                     gui::GameOverResult::NoSelection => {}
                     gui::GameOverResult::QuitToMenu => {
@@ -248,11 +347,11 @@ impl GameState for State {
             RunState::MagicMapReveal { row } => {
                 let mut map = self.ecs.fetch_mut::<Map>();
 
-                for x in 0..MAPWIDTH {
-                    let idx = map.xy_idx(x as i32, row);
+                for x in 0..map.width {
+                    let idx = map.xy_idx(x, row);
                     map.revealed_tiles[idx] = true;
                 }
-                if row as usize == MAPHEIGHT - 1 {
+                if row == map.height - 1 {
                     newrunstate = RunState::MonsterTurn;
                 } else {
                     newrunstate = RunState::MagicMapReveal { row: row + 1 };
@@ -312,6 +411,9 @@ impl State {
     }
 
     fn goto_next_level(&mut self) {
+        auto_action::stop(&mut self.ecs);
+        self.ecs.fetch_mut::<targeting::LastTarget>().entity = None;
+
         // Delete entities that aren't the player or his/her equipment
         let to_delete = self.entities_to_remove_on_level_change();
         for target in to_delete {
@@ -321,10 +423,14 @@ impl State {
         // Build a new map and place the player
         let worldmap;
         let current_depth;
+        let vault_spawns;
+        let final_depth = self.ecs.fetch::<settings::GameSettings>().final_depth;
         {
             let mut worldmap_resource = self.ecs.write_resource::<Map>();
             current_depth = worldmap_resource.depth;
-            *worldmap_resource = Map::new_map_rooms_and_corridors(current_depth + 1);
+            *worldmap_resource = Map::new_map_rooms_and_corridors(current_depth + 1, final_depth);
+            let mut rng = self.ecs.write_resource::<rltk::RandomNumberGenerator>();
+            vault_spawns = vaults::stamp_vault(&mut worldmap_resource, final_depth, &mut rng);
             worldmap = worldmap_resource.clone();
         }
 
@@ -332,6 +438,30 @@ impl State {
         for room in worldmap.rooms.iter().skip(1) {
             spawner::spawn_room(&mut self.ecs, room, current_depth + 1);
         }
+        spawner::maybe_spawn_merchant(&mut self.ecs, &worldmap);
+        spawner::spawn_vault(&mut self.ecs, &vault_spawns, current_depth + 1);
+        spawner::spawn_doors(&mut self.ecs, &worldmap);
+        spawner::spawn_lights(&mut self.ecs, &worldmap);
+
+        // The final level has the McGuffin in place of the stairs, in the throne room if it could be placed
+        let mcguffin_in_vault = vault_spawns.iter().any(|(_, _, glyph)| *glyph == 'M');
+        if current_depth + 1 == final_depth && !mcguffin_in_vault {
+            let (mcguffin_x, mcguffin_y) = worldmap.rooms[worldmap.rooms.len() - 1].center();
+            spawner::mcguffin(&mut self.ecs, mcguffin_x, mcguffin_y);
+        }
+
+        // A boss whose lair couldn't be placed waits in the last room instead, beside the McGuffin on the final level
+        if let Some(boss) = vaults::boss_for_depth(current_depth + 1, final_depth) {
+            if !vault_spawns.iter().any(|(_, _, glyph)| *glyph == boss) {
+                let (boss_x, boss_y) = worldmap.rooms[worldmap.rooms.len() - 1].center();
+                spawner::spawn_vault(&mut self.ecs, &[(boss_x + 1, boss_y, boss)], current_depth + 1);
+            }
+        }
+
+        {
+            let mut run_stats = self.ecs.write_resource::<run_stats::RunStats>();
+            run_stats.deepest_depth = i32::max(run_stats.deepest_depth, current_depth + 1);
+        }
 
         // Place the player and update resources
         let (player_x, player_y) = worldmap.rooms[0].center();
@@ -353,8 +483,8 @@ impl State {
         }
 
         // Notify the player and give them some health
-        let mut gamelog = self.ecs.fetch_mut::<game_log::GameLog>();
-        gamelog.entries.push("You descend to the next level, and take a moment to heal.".to_string());
+        let mut gamelog = self.ecs.fetch_mut::<GameLog>();
+        gamelog.push(LogCategory::General, "You descend to the next level, and take a moment to heal.");
         let mut player_health_store = self.ecs.write_storage::<CombatStats>();
         let player_health = player_health_store.get_mut(*player_entity);
         if let Some(player_health) = player_health {
@@ -363,6 +493,9 @@ impl State {
     }
 
     fn game_over_cleanup(&mut self) {
+        auto_action::stop(&mut self.ecs);
+        self.ecs.fetch_mut::<targeting::LastTarget>().entity = None;
+
         // Delete everything
         let mut to_delete = Vec::new();
         for e in self.ecs.entities().join() {
@@ -372,17 +505,25 @@ impl State {
             self.ecs.delete_entity(*del).expect("Deletion failed");
         }
 
-        // Build a new map and place the player
+        // Build a new town and place the player
         let worldmap;
         {
             let mut worldmap_resource = self.ecs.write_resource::<Map>();
-            *worldmap_resource = Map::new_map_rooms_and_corridors(1);
+            *worldmap_resource = Map::new_map_town();
             worldmap = worldmap_resource.clone();
         }
 
-        // spawn bad guys
-        for room in worldmap.rooms.iter().skip(1) {
-            spawner::spawn_room(&mut self.ecs, room, 1);
+        // Populate the town
+        spawner::spawn_town(&mut self.ecs, &worldmap);
+
+        // Start the run statistics and the log over, and pick up any change to the settings
+        {
+            let mut run_stats = self.ecs.write_resource::<run_stats::RunStats>();
+            *run_stats = run_stats::RunStats::default();
+            *self.ecs.write_resource::<settings::GameSettings>() = settings::GameSettings::load();
+            let mut gamelog = self.ecs.write_resource::<GameLog>();
+            *gamelog = GameLog::new();
+            gamelog.push(LogCategory::General, "Welcome to McGuffin Quest");
         }
 
         // Place the player and update resources
@@ -447,7 +588,8 @@ fn main() -> rltk::BError {
     context.with_mouse_visibility(false);
 
     let mut gs = State {
-        ecs: World::new()
+        ecs: World::new(),
+        gamepad: input::Gamepad::new()
     };
 
     gs.ecs.register::<Position>();
@@ -456,6 +598,7 @@ fn main() -> rltk::BError {
     gs.ecs.register::<Viewshed>();
     gs.ecs.register::<Monster>();
     gs.ecs.register::<Name>();
+    gs.ecs.register::<Description>();
     gs.ecs.register::<BlocksTile>();
     gs.ecs.register::<CombatStats>();
     gs.ecs.register::<WantsToMelee>();
@@ -486,27 +629,60 @@ fn main() -> rltk::BError {
     gs.ecs.register::<EntryTrigger>();
     gs.ecs.register::<EntityMoved>();
     gs.ecs.register::<SingleActivation>();
+    gs.ecs.register::<ItemValue>();
+    gs.ecs.register::<Wallet>();
+    gs.ecs.register::<GoldPile>();
+    gs.ecs.register::<Vendor>();
+    gs.ecs.register::<Npc>();
+    gs.ecs.register::<Dialogue>();
+    gs.ecs.register::<McGuffin>();
+    gs.ecs.register::<Boss>();
+    gs.ecs.register::<TeleportsVictim>();
+    gs.ecs.register::<Alarm>();
+    gs.ecs.register::<Pit>();
+    gs.ecs.register::<InflictsPoison>();
+    gs.ecs.register::<InflictsConfusion>();
+    gs.ecs.register::<Poisoned>();
+    gs.ecs.register::<Asleep>();
+    gs.ecs.register::<Door>();
+    gs.ecs.register::<BlocksVisibility>();
+    gs.ecs.register::<LockedDoor>();
+    gs.ecs.register::<Key>();
+    gs.ecs.register::<Slowed>();
+    gs.ecs.register::<DiggingTool>();
+    gs.ecs.register::<LightSource>();
+    gs.ecs.register::<Investigating>();
+    gs.ecs.register::<Sneaking>();
+    gs.ecs.register::<LastSeen>();
 
     gs.ecs.insert(SimpleMarkerAllocator::<SerializeMe>::new());
 
-    let map: Map = Map::new_map_rooms_and_corridors(1);
+    gs.ecs.insert(rltk::RandomNumberGenerator::new());
+
+    let map: Map = Map::new_map_town();
     let(player_x, player_y) = map.rooms[0].center();
 
     let player_entity = player(&mut gs.ecs, player_x, player_y);
 
-    gs.ecs.insert(rltk::RandomNumberGenerator::new());
-
-    for room in map.rooms.iter().skip(1) {
-        spawn_room(&mut gs.ecs, room, 1);
-    }
+    gs.ecs.insert(map.clone());
+    spawner::spawn_town(&mut gs.ecs, &map);
 
-    gs.ecs.insert(map);
     gs.ecs.insert(Point::new(player_x, player_y));
     gs.ecs.insert(player_entity);
     gs.ecs.insert(RunState::MainMenu { menu_selection: gui::MainMenuSelection::NewGame });
-    gs.ecs.insert(game_log::GameLog{ entries: vec!["Welcome to McGuffin Quest".to_string()]});
+    let mut gamelog = GameLog::new();
+    gamelog.push(LogCategory::General, "Welcome to McGuffin Quest");
+    gs.ecs.insert(gamelog);
     gs.ecs.insert(particle_system::ParticleBuilder::new());
+    gs.ecs.insert(noise_system::NoiseBuilder::new());
+    gs.ecs.insert(pathfinding::FlowFields::new());
+    gs.ecs.insert(auto_action::AutoPilot::new());
+    gs.ecs.insert(targeting::LastTarget::new());
+    gs.ecs.insert(keymap::Keymap::load());
+    gs.ecs.insert(input::Input::new());
     gs.ecs.insert(rex_assets::RexAssets::new());
+    gs.ecs.insert(run_stats::RunStats::default());
+    gs.ecs.insert(settings::GameSettings::load());
 
     rltk::main_loop(context, gs)
 }
@@ -1,20 +1,31 @@
-use rltk::{Algorithm2D, BaseMap, Point, RGB, Rltk, RandomNumberGenerator};
+use rltk::{Algorithm2D, BaseMap, Point, RandomNumberGenerator};
 use std::cmp::{max, min};
 use serde::{Serialize, Deserialize};
 use specs::prelude::*;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-use super::colors::*;
-use super::glyph_index::{AETHER_GLYPH, FLOOR_GLYPH, STAIRS_GLYPH};
 use super::rect::Rect;
+use super::terrain::terrain;
 
-pub const MAPWIDTH: usize = 80;
-pub const MAPHEIGHT: usize = 40;
-pub const MAPCOUNT: usize = MAPHEIGHT * MAPWIDTH;
+/// Tiles any darker than this can't be seen, even inside field of view.
+pub const MIN_VISIBLE_LIGHT: f32 = 0.25;
+
+/// How much digging a wall takes before it gives way.
+pub const WALL_TOUGHNESS: i32 = 4;
+
+/// How many turns the player's scent lingers on a tile.
+pub const SCENT_STRENGTH: i32 = 30;
 
 #[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum TileType {
-    Wall, Floor, DownStairs
+    Wall, Floor, DownStairs, ShallowWater, DeepWater, Lava, Chasm, TallGrass, Rubble
+}
+
+/// What the player last saw lying on a tile, drawn there while it is out of sight.
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub struct Remembered {
+    pub glyph: rltk::FontCharType,
+    pub item: bool
 }
 
 #[derive(Default, Serialize, Deserialize, Clone)]
@@ -28,6 +39,22 @@ pub struct Map {
     pub blocked: Vec<bool>,
     pub depth: i32,
     pub bloodstains: HashSet<usize>,
+    pub wall_damage: HashMap<usize, i32>,
+    pub outdoors: bool,
+    pub scent: Vec<i32>,
+    pub remembered: HashMap<usize, Remembered>,
+
+    #[serde(skip_serializing)]
+    #[serde(skip_deserializing)]
+    pub light: Vec<(f32, f32, f32)>,
+
+    #[serde(skip_serializing)]
+    #[serde(skip_deserializing)]
+    pub view_blocked: HashSet<usize>,
+
+    #[serde(skip_serializing)]
+    #[serde(skip_deserializing)]
+    pub openable_doors: HashSet<usize>,
 
     #[serde(skip_serializing)]
     #[serde(skip_deserializing)]
@@ -66,15 +93,38 @@ impl Map {
         }
     }
 
-    fn is_exit_valid(&self, x: i32, y: i32) -> bool {
+    fn is_exit_valid(&self, x: i32, y: i32, passable: &impl Fn(usize) -> bool) -> bool {
         if x < 1 || x > self.width - 1 || y < 1 || y > self.height - 1 { return false; }
-        let idx = self.xy_idx(x, y);
-        !self.blocked[idx]
+        passable(self.xy_idx(x, y))
+    }
+
+    /// The neighbouring tiles `passable` allows a step onto, with what each step costs.
+    pub fn exits_where(&self, idx: usize, passable: impl Fn(usize) -> bool) -> rltk::SmallVec<[(usize, f32); 10]> {
+        let mut exits = rltk::SmallVec::new();
+
+        let x = idx as i32 % self.width;
+        let y = idx as i32 / self.width;
+        let w = self.width as usize;
+        let cost = |i: usize| terrain(self.tiles[i]).cost;
+
+        // Cardinal directions
+        if self.is_exit_valid(x - 1, y, &passable) { exits.push((idx - 1, cost(idx - 1))) };
+        if self.is_exit_valid(x + 1, y, &passable) { exits.push((idx + 1, cost(idx + 1))) };
+        if self.is_exit_valid(x, y - 1, &passable) { exits.push((idx - w, cost(idx - w))) };
+        if self.is_exit_valid(x, y + 1, &passable) { exits.push((idx + w, cost(idx + w))) };
+
+        // Diagonals
+        if self.is_exit_valid(x - 1, y - 1, &passable) { exits.push(((idx - w) - 1, cost((idx - w) - 1) * 1.45)) };
+        if self.is_exit_valid(x + 1, y - 1, &passable) { exits.push(((idx - w) + 1, cost((idx - w) + 1) * 1.45)) };
+        if self.is_exit_valid(x - 1, y + 1, &passable) { exits.push(((idx + w) - 1, cost((idx + w) - 1) * 1.45)) };
+        if self.is_exit_valid(x + 1, y + 1, &passable) { exits.push(((idx + w) + 1, cost((idx + w) + 1) * 1.45)) };
+
+        exits
     }
 
     pub fn populate_blocked(&mut self) {
         for (i, tile) in self.tiles.iter_mut().enumerate() {
-            self.blocked[i] = *tile == TileType::Wall;
+            self.blocked[i] = !terrain(*tile).walkable;
         }
     }
 
@@ -84,19 +134,238 @@ impl Map {
         }
     }
 
-    pub fn new_map_rooms_and_corridors(new_depth: i32) -> Map {
-        let mut map = Map{
-            tiles: vec![TileType::Wall; MAPCOUNT],
+    /// True if a tile has enough light on it to be seen.
+    pub fn is_lit(&self, idx: usize) -> bool {
+        let (r, g, b) = self.light[idx];
+        f32::max(r, f32::max(g, b)) >= MIN_VISIBLE_LIGHT
+    }
+
+    /// True for walls that aren't part of the map's outer edge.
+    pub fn is_diggable(&self, x: i32, y: i32) -> bool {
+        if x < 1 || x > self.width - 2 || y < 1 || y > self.height - 2 { return false; }
+        self.tiles[self.xy_idx(x, y)] == TileType::Wall
+    }
+
+    /// Chips away at a wall, returning true once it has given way to floor.
+    pub fn dig(&mut self, x: i32, y: i32, amount: i32) -> bool {
+        if !self.is_diggable(x, y) { return false; }
+        let idx = self.xy_idx(x, y);
+        let damage = self.wall_damage.entry(idx).or_insert(0);
+        *damage += amount;
+        if *damage >= WALL_TOUGHNESS {
+            self.wall_damage.remove(&idx);
+            self.tiles[idx] = TileType::Floor;
+            return true;
+        }
+        false
+    }
+
+    /// Leaves a few walls next to open ground already cracked, ready to be blown through.
+    fn crack_walls(&mut self, rng: &mut RandomNumberGenerator) {
+        for y in 1 .. self.height - 1 {
+            for x in 1 .. self.width - 1 {
+                if !self.is_diggable(x, y) { continue; }
+                let next_to_floor = [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)].iter()
+                    .any(|(nx, ny)| self.tiles[self.xy_idx(*nx, *ny)] != TileType::Wall);
+                if next_to_floor && rng.roll_dice(1, 40) == 1 {
+                    let idx = self.xy_idx(x, y);
+                    self.wall_damage.insert(idx, 1);
+                }
+            }
+        }
+    }
+
+    /// Finds the gaps where corridors break through a room's walls, with wall on
+    /// either side so a door fits snugly.
+    pub fn doorways(&self) -> Vec<(i32, i32)> {
+        let mut doorways: HashSet<(i32, i32)> = HashSet::new();
+        for room in self.rooms.iter() {
+            for y in room.y1 ..= room.y2 + 1 {
+                for x in room.x1 ..= room.x2 + 1 {
+                    let on_horizontal_edge = y == room.y1 || y == room.y2 + 1;
+                    let on_vertical_edge = x == room.x1 || x == room.x2 + 1;
+                    if on_horizontal_edge == on_vertical_edge { continue; } // Corners and the interior
+                    if x < 1 || x > self.width - 2 || y < 1 || y > self.height - 2 { continue; }
+                    if self.tiles[self.xy_idx(x, y)] != TileType::Floor { continue; }
+
+                    let (side_a, side_b) = if on_horizontal_edge {
+                        (self.xy_idx(x - 1, y), self.xy_idx(x + 1, y))
+                    } else {
+                        (self.xy_idx(x, y - 1), self.xy_idx(x, y + 1))
+                    };
+                    if self.tiles[side_a] == TileType::Wall && self.tiles[side_b] == TileType::Wall {
+                        doorways.insert((x, y));
+                    }
+                }
+            }
+        }
+        doorways.into_iter().collect()
+    }
+
+    /// Fills part of a room with special terrain. Pools, lava and chasms stay off the
+    /// room's outer ring so there is always a way around them.
+    fn apply_room_feature(&mut self, room: &Rect, final_depth: i32, rng: &mut RandomNumberGenerator) {
+        let inner = Rect{ x1: room.x1 + 2, y1: room.y1 + 2, x2: room.x2 - 1, y2: room.y2 - 1 };
+        let (center_x, center_y) = room.center();
+
+        match rng.roll_dice(1, 10) {
+            1 => {
+                for y in inner.y1 ..= inner.y2 {
+                    for x in inner.x1 ..= inner.x2 {
+                        let idx = self.xy_idx(x, y);
+                        let deep = x > inner.x1 && x < inner.x2 && y > inner.y1 && y < inner.y2;
+                        self.tiles[idx] = if deep { TileType::DeepWater } else { TileType::ShallowWater };
+                    }
+                }
+            }
+            2 if self.depth >= 4 => {
+                for y in inner.y1 ..= inner.y2 {
+                    for x in inner.x1 ..= inner.x2 {
+                        let idx = self.xy_idx(x, y);
+                        self.tiles[idx] = TileType::Lava;
+                    }
+                }
+            }
+            3 if self.depth >= 3 && self.depth < final_depth => {
+                for y in center_y - 1 ..= center_y + 1 {
+                    for x in center_x - 1 ..= center_x + 1 {
+                        let idx = self.xy_idx(x, y);
+                        self.tiles[idx] = TileType::Chasm;
+                    }
+                }
+            }
+            4 | 5 => {
+                for y in room.y1 + 1 ..= room.y2 {
+                    for x in room.x1 + 1 ..= room.x2 {
+                        let idx = self.xy_idx(x, y);
+                        if self.tiles[idx] == TileType::Floor && rng.roll_dice(1, 2) == 1 {
+                            self.tiles[idx] = TileType::TallGrass;
+                        }
+                    }
+                }
+            }
+            6 => {
+                for y in room.y1 + 1 ..= room.y2 {
+                    for x in room.x1 + 1 ..= room.x2 {
+                        let idx = self.xy_idx(x, y);
+                        if self.tiles[idx] == TileType::Floor && rng.roll_dice(1, 3) == 1 {
+                            self.tiles[idx] = TileType::Rubble;
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Creates a map of solid wall at the given depth, sized for that depth.
+    fn new(new_depth: i32) -> Map {
+        let (width, height) = level_size(new_depth);
+        let count = (width * height) as usize;
+        Map{
+            tiles: vec![TileType::Wall; count],
             rooms: Vec::new(),
-            width: MAPWIDTH as i32,
-            height: MAPHEIGHT as i32,
-            revealed_tiles: vec![false; MAPCOUNT],
-            visible_tiles: vec![false; MAPCOUNT],
-            blocked: vec![false; MAPCOUNT],
-            tile_content: vec![Vec::new(); MAPCOUNT],
+            width,
+            height,
+            revealed_tiles: vec![false; count],
+            visible_tiles: vec![false; count],
+            blocked: vec![false; count],
+            tile_content: vec![Vec::new(); count],
             depth: new_depth,
-            bloodstains: HashSet::new()
-        };
+            bloodstains: HashSet::new(),
+            wall_damage: HashMap::new(),
+            outdoors: false,
+            light: vec![(0.0, 0.0, 0.0); count],
+            scent: vec![0; count],
+            remembered: HashMap::new(),
+            view_blocked: HashSet::new(),
+            openable_doors: HashSet::new()
+        }
+    }
+
+    /// Clears the per-turn state that isn't saved, sized to fit this map.
+    pub fn reset_transient(&mut self) {
+        let count = (self.width * self.height) as usize;
+        self.tile_content = vec![Vec::new(); count];
+        self.light = vec![(0.0, 0.0, 0.0); count];
+    }
+
+    fn apply_building(&mut self, building: &Rect, door_on_top: bool) {
+        for y in building.y1 ..= building.y2 {
+            for x in building.x1 ..= building.x2 {
+                let idx = self.xy_idx(x, y);
+                if x == building.x1 || x == building.x2 || y == building.y1 || y == building.y2 {
+                    self.tiles[idx] = TileType::Wall;
+                } else {
+                    self.tiles[idx] = TileType::Floor;
+                }
+            }
+        }
+
+        let (door_x, _) = building.center();
+        let door_y = if door_on_top { building.y1 } else { building.y2 };
+        let door_idx = self.xy_idx(door_x, door_y);
+        self.tiles[door_idx] = TileType::Floor;
+    }
+
+    /// Builds the surface town at depth 0. The first room is the town square the
+    /// player starts in, the rest are buildings with a door facing the square.
+    pub fn new_map_town() -> Map {
+        let mut map = Map::new(0);
+        map.outdoors = true;
+
+        // An open field fenced in by the map edge
+        for y in 0..map.height {
+            for x in 0..map.width {
+                let idx = map.xy_idx(x, y);
+                if x == 0 || x == map.width - 1 || y == 0 || y == map.height - 1 {
+                    map.tiles[idx] = TileType::Wall;
+                } else {
+                    map.tiles[idx] = TileType::Floor;
+                }
+            }
+        }
+
+        let square = Rect::new(map.width / 2 - 7, map.height / 2 - 4, 14, 8);
+        map.rooms.push(square);
+
+        // Keep the ground around the dungeon entrance clear
+        let entrance = Rect::new(map.width - 10, map.height / 2 - 3, 7, 6);
+
+        const MAX_BUILDINGS: usize = 8;
+        let mut rng = RandomNumberGenerator::new();
+
+        for _ in 0..40 {
+            if map.rooms.len() > MAX_BUILDINGS { break; }
+
+            let w: i32 = rng.range(7, 13);
+            let h: i32 = rng.range(5, 9);
+            let x: i32 = rng.range(2, map.width - w - 2);
+            let y: i32 = rng.range(2, map.height - h - 2);
+
+            let building = Rect::new(x, y, w, h);
+            // Leave a lane of open ground around every building
+            let footprint = Rect::new(x - 1, y - 1, w + 2, h + 2);
+            let mut ok = !footprint.intersect(&entrance);
+            for other in map.rooms.iter() {
+                if footprint.intersect(other) { ok = false; }
+            }
+            if ok {
+                let (_, square_y) = square.center();
+                map.apply_building(&building, building.y1 > square_y);
+                map.rooms.push(building);
+            }
+        }
+
+        let (stairs_x, stairs_y) = entrance.center();
+        let stairs_idx = map.xy_idx(stairs_x, stairs_y);
+        map.tiles[stairs_idx] = TileType::DownStairs;
+
+        map
+    }
+
+    pub fn new_map_rooms_and_corridors(new_depth: i32, final_depth: i32) -> Map {
+        let mut map = Map::new(new_depth);
 
         const MAX_ROOMS: i32 = 30;
         const MIN_SIZE: i32 = 6;
@@ -134,15 +403,36 @@ impl Map {
             }
         }
 
-        let stairs_position = map.rooms[map.rooms.len() - 1].center();
-        let stairs_idx = map.xy_idx(stairs_position.0, stairs_position.1);
-        map.tiles[stairs_idx] = TileType::DownStairs;
+        // Dress up the rooms between the start and the stairs
+        let feature_rooms: Vec<Rect> = map.rooms.iter().skip(1).take(map.rooms.len().saturating_sub(2)).copied().collect();
+        for room in feature_rooms.iter() {
+            map.apply_room_feature(room, final_depth, &mut rng);
+        }
+
+        map.crack_walls(&mut rng);
+
+        // The McGuffin waits where the stairs would be on the final level
+        if new_depth < final_depth {
+            let stairs_position = map.rooms[map.rooms.len() - 1].center();
+            let stairs_idx = map.xy_idx(stairs_position.0, stairs_position.1);
+            map.tiles[stairs_idx] = TileType::DownStairs;
+        }
     
         map
     }
 
 }
 
+/// How big the map is at each depth. The town fits on one screen; the dungeon opens
+/// out the deeper it goes.
+fn level_size(depth: i32) -> (i32, i32) {
+    match depth {
+        0 ..= 3 => (80, 40),
+        4 ..= 6 => (100, 50),
+        _ => (120, 60)
+    }
+}
+
 pub fn is_inbounds(map: &Map, x: i32, y: i32) -> bool {
     if x < 0 || x > map.width - 1 || y < 0 || y > map.height - 1 { return false; } else { return true;}
 }
@@ -152,7 +442,7 @@ fn is_revealed_and_wall(map: &Map, x: i32, y: i32) -> bool {
     map.tiles[idx] == TileType::Wall && map.revealed_tiles[idx]
 }
 
-fn wall_glyph(map: &Map, x: i32, y: i32) -> rltk::FontCharType {
+pub fn wall_glyph(map: &Map, x: i32, y: i32) -> rltk::FontCharType {
     if x < 1 || x > map.width - 2 || y < 1 || y > map.height - 2 as i32 { return 35; }
     let mut mask: u8 = 0;
 
@@ -191,7 +481,7 @@ impl Algorithm2D for Map {
 
 impl BaseMap for Map {
     fn is_opaque(&self, idx: usize) -> bool {
-        self.tiles[idx as usize] == TileType::Wall        
+        terrain(self.tiles[idx]).opaque || self.view_blocked.contains(&idx)
     }
 
     fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
@@ -204,75 +494,6 @@ impl BaseMap for Map {
     }
 
     fn get_available_exits(&self, idx: usize) -> rltk::SmallVec<[(usize, f32); 10]> {
-        let mut exits = rltk::SmallVec::new();
-
-        let x = idx as i32 % self.width;
-        let y = idx as i32 / self.width;
-        let w = self.width as usize;
-
-        // Cardinal directions
-        if self.is_exit_valid(x - 1, y) { exits.push((idx - 1, 1.0)) };
-        if self.is_exit_valid(x + 1, y) { exits.push((idx + 1, 1.0)) };
-        if self.is_exit_valid(x, y - 1) { exits.push((idx - w, 1.0)) };
-        if self.is_exit_valid(x, y + 1) { exits.push((idx + w, 1.0)) };
-
-        // Diagonals
-        if self.is_exit_valid(x - 1, y - 1) { exits.push(((idx - w) - 1, 1.45)) };
-        if self.is_exit_valid(x + 1, y - 1) { exits.push(((idx - w) + 1, 1.45)) };
-        if self.is_exit_valid(x - 1, y + 1) { exits.push(((idx + w) - 1, 1.45)) };
-        if self.is_exit_valid(x + 1, y + 1) { exits.push(((idx + w) + 1, 1.45)) };
-
-        exits   
+        self.exits_where(idx, |i| !self.blocked[i])
     }
 }
-
-pub fn draw_map(ecs: &World, ctx: &mut Rltk) {
-
-    let map = ecs.fetch::<Map>();
-
-    let mut y = 0;
-    let mut x = 0;
-
-    for (idx, tile) in map.tiles.iter().enumerate() {
-        // Render a tile depending upon the tile type
-        if map.revealed_tiles[idx] {
-            let glyph;
-            let mut fg: RGB;
-            let mut bg: RGB = return_rgb(DEFAULT_BG);
-            // Render a tile depending upon the tile type
-            match tile {
-                TileType::Floor => {
-                    glyph = rltk::to_cp437(FLOOR_GLYPH);
-                    fg = return_rgb(FLOOR_COLOR);
-                }
-                TileType::Wall => {
-                    glyph = wall_glyph(&*map, x, y);
-                    fg = return_rgb(WALL_COLOR);
-                }
-                TileType::DownStairs => {
-                    glyph = rltk::to_cp437(STAIRS_GLYPH);
-                    fg = return_rgb(STAIRS_FG);
-                }
-            }
-            if map.bloodstains.contains(&idx) { bg = return_rgb(BLOOD_BG);}
-            if !map.visible_tiles[idx] { 
-                fg = return_rgb(OUT_OF_VIEW);
-                bg = return_rgb(DEFAULT_BG);
-            } 
-            ctx.set(x, y, fg, bg, glyph);
-        } else {
-            let glyph;
-            let fg = return_rgb(AETHER_FG);
-            let bg = return_rgb(DEFAULT_BG);
-            glyph = rltk::to_cp437(AETHER_GLYPH);
-            ctx.set(x, y, fg, bg, glyph);
-        }
-        
-        // Move the coordinates
-        x += 1;
-        if x > MAPWIDTH as i32 - 1 {
-            x = 0;
-            y += 1;
-        }
-    }
-}
\ No newline at end of file
@@ -1,20 +1,31 @@
 use specs::prelude::*;
 
-use super::{BlocksTile, Map, Position};
+use super::{BlocksTile, BlocksVisibility, Door, Hidden, LockedDoor, Map, pathfinding::FlowFields, Position};
 
 pub struct MapIndexingSystem {}
 
 impl<'a> System<'a> for MapIndexingSystem {
+    #[allow(clippy::type_complexity)]
     type SystemData = ( WriteExpect<'a, Map>,
                         ReadStorage<'a, Position>,
                         ReadStorage<'a, BlocksTile>,
-                        Entities<'a>,);
+                        ReadStorage<'a, BlocksVisibility>,
+                        ReadStorage<'a, Door>,
+                        ReadStorage<'a, LockedDoor>,
+                        ReadStorage<'a, Hidden>,
+                        Entities<'a>,
+                        WriteExpect<'a, FlowFields>);
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut map, position, blockers, entities) = data;
+        let (mut map, position, blockers, view_blockers, doors, locks, hidden, entities, mut flow_fields) = data;
+
+        // Blockers are about to move, so any cached paths are stale
+        flow_fields.invalidate();
 
         map.populate_blocked();
         map.clear_content_index();
+        map.view_blocked.clear();
+        map.openable_doors.clear();
         for (entity, position) in (&entities, &position).join() {
             let idx = map.xy_idx(position.x, position.y);
 
@@ -23,6 +34,16 @@ impl<'a> System<'a> for MapIndexingSystem {
                 map.blocked[idx] = true;
             }
 
+            if view_blockers.get(entity).is_some() {
+                map.view_blocked.insert(idx);
+            }
+
+            // Closed doors the player could walk through by opening them
+            let closed = doors.get(entity).map_or(false, |door| !door.open);
+            if closed && locks.get(entity).is_none() && hidden.get(entity).is_none() {
+                map.openable_doors.insert(idx);
+            }
+
             // Push the entity to the appropriate index slot. It's a Copy
             // type, so we don't need to clone it (we want to avoid moving it out of the ECS!)
             map.tile_content[idx].push(entity);
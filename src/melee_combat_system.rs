@@ -2,11 +2,16 @@ use specs::prelude::*;
 
 use crate::colors::return_rgb;
 
-use super::{CombatStats, DefenseBonus, Equipped, game_log::GameLog, glyph_index::POW_GLYPH, HungerClock, HungerState, WantsToMelee, MeleePowerBonus, Name,
-             particle_system::ParticleBuilder, Position, SufferDamage};
+use super::{CombatStats, DefenseBonus, Equipped, game_log::{GameLog, LogCategory, LogEntry}, glyph_index::POW_GLYPH, HungerClock, HungerState, WantsToMelee, MeleePowerBonus, Name,
+             noise_system::{COMBAT_NOISE, NoiseBuilder}, particle_system::ParticleBuilder, Position, SufferDamage};
 
 use super::colors::{POW_FG, DEFAULT_BG};
 
+/// The extra attack power a fighter gets from how well they have eaten.
+pub fn hunger_power_bonus(state: HungerState) -> i32 {
+    if state == HungerState::WellFed { 1 } else { 0 }
+}
+
 pub struct MeleeCombatSystem {}
 
 impl<'a> System<'a> for MeleeCombatSystem {
@@ -21,12 +26,13 @@ impl<'a> System<'a> for MeleeCombatSystem {
                         ReadStorage<'a, Equipped>,
                         WriteExpect<'a, ParticleBuilder>,
                         ReadStorage<'a, Position>,
-                        ReadStorage<'a, HungerClock>
+                        ReadStorage<'a, HungerClock>,
+                        WriteExpect<'a, NoiseBuilder>
                     );
     fn run(&mut self, data: Self::SystemData) {
         let (entities, mut log, mut wants_melee, names,
              combat_stats, mut inflict_damage, melee_power_bonuses, defense_bonuses,
-             equipped, mut particle_builder, positions, hunger_clock) = data;
+             equipped, mut particle_builder, positions, hunger_clock, mut noise) = data;
 
         for (entity, wants_melee, name, stats) in (&entities, &wants_melee, &names, &combat_stats).join() {
             if stats.hp > 0 {
@@ -39,9 +45,7 @@ impl<'a> System<'a> for MeleeCombatSystem {
 
                 let hc = hunger_clock.get(entity);
                 if let Some(hc) = hc {
-                    if hc.state == HungerState::WellFed {
-                        offensive_bonus += 1;
-                    }
+                    offensive_bonus += hunger_power_bonus(hc.state);
                 }
 
                 let target_stats = combat_stats.get(wants_melee.target).unwrap();
@@ -58,14 +62,15 @@ impl<'a> System<'a> for MeleeCombatSystem {
                     let pos = positions.get(wants_melee.target);
                     if let Some(pos) = pos {
                         particle_builder.request(pos.x, pos.y, return_rgb(POW_FG), return_rgb(DEFAULT_BG), rltk::to_cp437(POW_GLYPH), 200.0);
+                        noise.make_noise(pos.x, pos.y, COMBAT_NOISE);
                     }
 
                     let damage = i32::max(0, (stats.power + offensive_bonus) - (target_stats.defense + defensive_bonus));
 
                     if damage == 0 {
-                        log.entries.push(format!("{} is unable to hurt {}", &name.name, &target_name.name));
+                        log.add(LogEntry::new(LogCategory::Combat).name(&name.name).text(" is unable to hurt ").name(&target_name.name));
                     } else {
-                        log.entries.push(format!("{} hits {}, for {} hp.", &name.name, &target_name.name, damage));
+                        log.add(LogEntry::new(LogCategory::Combat).name(&name.name).text(" hits ").name(&target_name.name).text(format!(", for {} hp.", damage)));
                         SufferDamage::new_damage(&mut inflict_damage, wants_melee.target, damage);
                     }
                 }
@@ -1,6 +1,7 @@
 use rltk::{Point, RGB};
 use specs::prelude::*;
-use super::{colors::{return_rgb, CONFUSION_FG, DEFAULT_BG}, Confusion, glyph_index::CONFUSION_GLYPH, Map, Monster, particle_system::ParticleBuilder, Position, RunState, Viewshed, WantsToMelee};
+use super::{Asleep, Boss, CombatStats, Investigating, Slowed, colors::{return_rgb, CONFUSION_FG, DEFAULT_BG}, Confusion, EntityMoved, glyph_index::CONFUSION_GLYPH, Map, Monster,
+            particle_system::ParticleBuilder, pathfinding::{FlowFields, Goal, NEIGHBOURS}, Position, RunState, Viewshed, WantsToMelee};
 
 pub struct MonsterAI {}
 
@@ -16,18 +17,40 @@ impl<'a> System<'a> for MonsterAI {
                         WriteStorage<'a, Position>,
                         WriteStorage<'a, WantsToMelee>,
                         WriteStorage<'a, Confusion>,
-                        WriteExpect<'a, ParticleBuilder>
+                        WriteExpect<'a, ParticleBuilder>,
+                        WriteStorage<'a, Asleep>,
+                        WriteStorage<'a, EntityMoved>,
+                        WriteExpect<'a, rltk::RandomNumberGenerator>,
+                        WriteStorage<'a, Slowed>,
+                        WriteStorage<'a, Investigating>,
+                        WriteExpect<'a, FlowFields>,
+                        ReadStorage<'a, CombatStats>,
+                        ReadStorage<'a, Boss>
                     );
     
     fn run(&mut self, data: Self::SystemData) {
         let ( mut map, player_pos, player_entity, runstate,
               entities, mut viewshed, monster, mut position,
-              mut wants_to_melee, mut confused, mut particle_builder ) = data;
+              mut wants_to_melee, mut confused, mut particle_builder,
+              mut asleep, mut entity_moved, mut rng, mut slowed, mut investigating,
+              mut flow_fields, combat_stats, bosses ) = data;
 
         if *runstate != RunState::MonsterTurn { return; }
 
         for (entity, mut viewshed, _monster, mut pos) in (&entities, &mut viewshed, &monster, &mut position).join() {
             
+            // Sleeping monsters may be roused by the player blundering right up to them
+            if asleep.get(entity).is_some() {
+                let distance = rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *player_pos);
+                if distance < 1.5 && rng.roll_dice(1, 2) == 1 {
+                    asleep.remove(entity);
+                }
+                continue;
+            }
+
+            // Difficult terrain costs a turn
+            if slowed.remove(entity).is_some() { continue; }
+
             let mut can_act = true;
 
             let is_confused = confused.get_mut(entity);
@@ -46,28 +69,74 @@ impl<'a> System<'a> for MonsterAI {
             }
 
             if can_act {
+                let can_see_player = viewshed.visible_tiles.contains(&*player_pos);
+                let player_goal = Goal::Player{ x: player_pos.x, y: player_pos.y };
                 let distance = rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *player_pos);
-                if distance < 1.5 {
-                    // Attack goes here
+
+                // Badly hurt monsters run for it, unless they are too proud
+                let badly_hurt = combat_stats.get(entity).filter(|stats| stats.hp * 4 < stats.max_hp).is_some();
+                let fleeing = can_see_player && badly_hurt && bosses.get(entity).is_none();
+
+                let mut step: Option<(i32, i32)> = None;
+                if fleeing {
+                    step = flow_fields.flee_step(&map, player_goal, pos.x, pos.y);
+                }
+
+                if step.is_none() && distance < 1.5 {
+                    // Attack goes here; cornered monsters fight back
                     wants_to_melee.insert(entity, WantsToMelee { target: *player_entity }).expect("Unable to insert attack");
-                } else if viewshed.visible_tiles.contains(&*player_pos) {
-                
-                    let path = rltk::a_star_search(
-                        map.xy_idx(pos.x, pos.y) as i32,
-                        map.xy_idx(player_pos.x, player_pos.y) as i32,
-                        &mut *map
-                    );
-                    if path.success && path.steps.len() > 1 {
-                        let mut idx = map.xy_idx(pos.x, pos.y);
-                        map.blocked[idx] = false;
-                        pos.x = path.steps[1] as i32 % map.width;
-                        pos.y = path.steps[1] as i32 / map.width;
-                        idx = map.xy_idx(pos.x, pos.y);
-                        map.blocked[idx] = true;
-                        viewshed.dirty = true;
+                    continue;
+                }
+
+                // Chase the player if they can be seen, then follow their trail, then go and
+                // look into the last thing heard
+                if !fleeing && can_see_player {
+                    investigating.remove(entity);
+                    step = flow_fields.next_step(&map, player_goal, pos.x, pos.y);
+                }
+                if !fleeing && step.is_none() {
+                    step = scent_step(&map, pos.x, pos.y);
+                }
+                if !fleeing && step.is_none() {
+                    if let Some(spot) = investigating.get(entity) {
+                        step = flow_fields.next_step(&map, Goal::Towards{ x: spot.x, y: spot.y }, pos.x, pos.y);
+
+                        // Give up once there, or if there is no way to get there
+                        if step.filter(|next| *next != (spot.x, spot.y)).is_none() {
+                            investigating.remove(entity);
+                        }
                     }
                 }
+
+                if let Some((x, y)) = step {
+                    let mut idx = map.xy_idx(pos.x, pos.y);
+                    map.blocked[idx] = false;
+                    pos.x = x;
+                    pos.y = y;
+                    idx = map.xy_idx(pos.x, pos.y);
+                    map.blocked[idx] = true;
+                    entity_moved.insert(entity, EntityMoved{}).expect("Unable to insert marker");
+                    viewshed.dirty = true;
+                }
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// The open neighbouring tile with the freshest trace of the player's scent, if it is
+/// fresher than where the monster already stands.
+fn scent_step(map: &Map, x: i32, y: i32) -> Option<(i32, i32)> {
+    let mut best: Option<(i32, i32)> = None;
+    let mut best_scent = map.scent[map.xy_idx(x, y)];
+    for (dx, dy) in NEIGHBOURS.iter() {
+        let (nx, ny) = (x + dx, y + dy);
+        if nx < 0 || nx >= map.width || ny < 0 || ny >= map.height { continue; }
+        let idx = map.xy_idx(nx, ny);
+        if map.blocked[idx] { continue; }
+        if map.scent[idx] > best_scent {
+            best_scent = map.scent[idx];
+            best = Some((nx, ny));
+        }
+    }
+    best
+}
@@ -0,0 +1,79 @@
+use rltk::DijkstraMap;
+use specs::prelude::*;
+
+use super::{Asleep, Investigating, Map, Monster, pathfinding::distance_at, Position};
+
+/// Footsteps, doors creaking, steel on steel. Noise travels through walkable
+/// tiles, so a closed door or a solid wall muffles it completely.
+pub const FOOTSTEP_NOISE: i32 = 3;
+pub const SNEAKING_NOISE: i32 = 1;
+pub const RUNNING_NOISE: i32 = 5;
+pub const DOOR_NOISE: i32 = 6;
+pub const DIGGING_NOISE: i32 = 8;
+pub const COMBAT_NOISE: i32 = 8;
+pub const TRAP_NOISE: i32 = 8;
+pub const EXPLOSION_NOISE: i32 = 12;
+
+/// How much of a noise has to be left when it reaches a sleeper to wake it up.
+const WAKING_VOLUME: f32 = 4.0;
+
+struct NoiseRequest {
+    x: i32,
+    y: i32,
+    volume: i32
+}
+
+pub struct NoiseBuilder {
+    requests: Vec<NoiseRequest>
+}
+
+impl NoiseBuilder {
+    pub fn new() -> NoiseBuilder {
+        NoiseBuilder { requests: Vec::new() }
+    }
+
+    pub fn make_noise(&mut self, x: i32, y: i32, volume: i32) {
+        self.requests.push(NoiseRequest{ x, y, volume });
+    }
+}
+
+pub struct NoiseSystem {}
+
+impl<'a> System<'a> for NoiseSystem {
+    type SystemData = ( ReadExpect<'a, Map>,
+                        WriteExpect<'a, NoiseBuilder>,
+                        Entities<'a>,
+                        ReadStorage<'a, Monster>,
+                        ReadStorage<'a, Position>,
+                        WriteStorage<'a, Asleep>,
+                        WriteStorage<'a, Investigating>
+                      );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (map, mut noise_builder, entities, monsters, positions, mut asleep, mut investigating) = data;
+
+        for noise in noise_builder.requests.iter() {
+            if noise.volume < 1 { continue; }
+
+            let source_idx = map.xy_idx(noise.x, noise.y);
+            let sound = DijkstraMap::new(map.width, map.height, &[source_idx], &*map, noise.volume as f32);
+
+            for (entity, _monster, pos) in (&entities, &monsters, &positions).join() {
+                let distance = distance_at(&map, &sound.map, pos.x, pos.y);
+                if distance > noise.volume as f32 { continue; }
+
+                if asleep.get(entity).is_some() {
+                    if noise.volume as f32 - distance >= WAKING_VOLUME {
+                        asleep.remove(entity);
+                    } else {
+                        continue;
+                    }
+                }
+
+                investigating.insert(entity, Investigating{ x: noise.x, y: noise.y }).expect("Unable to insert investigation");
+            }
+        }
+
+        noise_builder.requests.clear();
+    }
+}
@@ -0,0 +1,44 @@
+use rltk::{Point, RandomNumberGenerator};
+use specs::prelude::*;
+use super::{Map, Npc, Position, RunState};
+
+pub struct NpcAI {}
+
+impl<'a> System<'a> for NpcAI {
+    type SystemData = ( WriteExpect<'a, Map>,
+                        ReadExpect<'a, Point>,
+                        ReadExpect<'a, RunState>,
+                        WriteExpect<'a, RandomNumberGenerator>,
+                        ReadStorage<'a, Npc>,
+                        WriteStorage<'a, Position>
+                    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut map, player_pos, runstate, mut rng, npcs, mut position) = data;
+
+        if *runstate != RunState::MonsterTurn { return; }
+
+        for (_npc, pos) in (&npcs, &mut position).join() {
+            // Townsfolk amble about rather than walking every turn
+            if rng.roll_dice(1, 3) != 1 { continue; }
+
+            let delta_x = rng.range(-1, 2);
+            let delta_y = rng.range(-1, 2);
+            if delta_x == 0 && delta_y == 0 { continue; }
+
+            let dest_x = pos.x + delta_x;
+            let dest_y = pos.y + delta_y;
+            if dest_x < 1 || dest_x > map.width - 2 || dest_y < 1 || dest_y > map.height - 2 { continue; }
+            if dest_x == player_pos.x && dest_y == player_pos.y { continue; }
+
+            let dest_idx = map.xy_idx(dest_x, dest_y);
+            if !map.blocked[dest_idx] {
+                let idx = map.xy_idx(pos.x, pos.y);
+                map.blocked[idx] = false;
+                pos.x = dest_x;
+                pos.y = dest_y;
+                map.blocked[dest_idx] = true;
+            }
+        }
+    }
+}
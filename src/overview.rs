@@ -0,0 +1,59 @@
+use rltk::{Point, RGB, Rltk};
+use specs::prelude::*;
+
+use super::colors::*;
+use super::glyph_index::*;
+use super::{Map, TileType, terrain::terrain};
+
+/// Draws the explored map shrunk to fit an area of the screen, with each cell standing
+/// for a square block of tiles.
+pub fn draw_overview(ecs: &World, ctx: &mut Rltk, left: i32, top: i32, width: i32, height: i32) {
+    let map = ecs.fetch::<Map>();
+    let player_pos = ecs.fetch::<Point>();
+    let bg = return_rgb(DEFAULT_BG);
+
+    let scale = i32::max(1, i32::max(div_ceil(map.width, width), div_ceil(map.height, height)));
+    let (columns, rows) = (div_ceil(map.width, scale), div_ceil(map.height, scale));
+
+    // Centre the shrunken map in the space given
+    let offset_x = left + (width - columns) / 2;
+    let offset_y = top + (height - rows) / 2;
+
+    for cell_y in 0 .. rows {
+        for cell_x in 0 .. columns {
+            if let Some((glyph, fg)) = cell(&map, *player_pos, cell_x * scale, cell_y * scale, scale) {
+                ctx.set(offset_x + cell_x, offset_y + cell_y, fg, bg, rltk::to_cp437(glyph));
+            }
+        }
+    }
+}
+
+/// What a block of tiles shows, most important first: the player, the way down, items the
+/// player remembers seeing, then whatever ground has been explored.
+fn cell(map: &Map, player_pos: Point, x0: i32, y0: i32, scale: i32) -> Option<(char, RGB)> {
+    let mut best: Option<(i32, char, (f32, f32, f32))> = None;
+    for y in y0 .. i32::min(y0 + scale, map.height) {
+        for x in x0 .. i32::min(x0 + scale, map.width) {
+            let idx = map.xy_idx(x, y);
+            let shown = if player_pos.x == x && player_pos.y == y {
+                (4, PLAYER_GLYPH, PLAYER_FG)
+            } else if !map.revealed_tiles[idx] {
+                continue;
+            } else if map.tiles[idx] == TileType::DownStairs {
+                (3, STAIRS_GLYPH, terrain(TileType::DownStairs).fg)
+            } else if map.remembered.get(&idx).filter(|memory| memory.item).is_some() {
+                (2, OVERVIEW_ITEM_GLYPH, OVERVIEW_ITEM_FG)
+            } else if terrain(map.tiles[idx]).walkable {
+                (1, OVERVIEW_FLOOR_GLYPH, OVERVIEW_FLOOR_FG)
+            } else {
+                (0, OVERVIEW_WALL_GLYPH, OVERVIEW_WALL_FG)
+            };
+            if best.filter(|(priority, _, _)| *priority >= shown.0).is_none() { best = Some(shown); }
+        }
+    }
+    best.map(|(_, glyph, fg)| (glyph, return_rgb(fg)))
+}
+
+fn div_ceil(a: i32, b: i32) -> i32 {
+    (a + b - 1) / b
+}
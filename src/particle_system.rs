@@ -1,6 +1,6 @@
 use rltk::{RGB, Rltk};
 use specs::prelude::*;
-use super::{ ParticleLifetime, Position, Renderable };
+use super::{ LightSource, ParticleLifetime, Position, Renderable };
 
 pub fn cull_dead_particles(ecs: &mut World, ctx: &Rltk) {
 
@@ -28,7 +28,8 @@ struct ParticleRequest {
     fg: RGB,
     bg: RGB,
     glyph: rltk::FontCharType,
-    lifetime: f32
+    lifetime: f32,
+    light: Option<LightSource>
 }
 
 pub struct ParticleBuilder {
@@ -43,7 +44,17 @@ impl ParticleBuilder {
     pub fn request(&mut self, x: i32, y: i32, fg: RGB, bg: RGB, glyph: rltk::FontCharType, lifetime: f32) {
         self.requests.push(
             ParticleRequest{
-                x, y, fg, bg, glyph, lifetime
+                x, y, fg, bg, glyph, lifetime, light: None
+            }
+        )
+    }
+
+    /// Requests a particle that also lights up its surroundings, like a fireball's flash.
+    #[allow(clippy::too_many_arguments)]
+    pub fn request_lit(&mut self, x: i32, y: i32, fg: RGB, bg: RGB, glyph: rltk::FontCharType, lifetime: f32, light: LightSource) {
+        self.requests.push(
+            ParticleRequest{
+                x, y, fg, bg, glyph, lifetime, light: Some(light)
             }
         )
     }
@@ -57,17 +68,21 @@ impl<'a> System<'a> for ParticleSpawnSystem {
                         WriteStorage<'a, Position>,
                         WriteStorage<'a, Renderable>,
                         WriteStorage<'a, ParticleLifetime>,
-                        WriteExpect<'a, ParticleBuilder>
+                        WriteExpect<'a, ParticleBuilder>,
+                        WriteStorage<'a, LightSource>
                        );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, mut positions, mut renderables, mut particles, mut particle_builder) = data;
+        let (entities, mut positions, mut renderables, mut particles, mut particle_builder, mut lights) = data;
 
         for new_particle in particle_builder.requests.iter() {
             let p = entities.create();
             positions.insert(p, Position{ x: new_particle.x, y: new_particle.y}).expect("Unable to insert position");
             renderables.insert(p, Renderable{fg: new_particle.fg, bg: new_particle.bg, glyph: new_particle.glyph, render_order: 0}).expect("Unable to insert renderable");
             particles.insert(p, ParticleLifetime { lifetime_ms: new_particle.lifetime }).expect("Unable to insert lifetime");
+            if let Some(light) = &new_particle.light {
+                lights.insert(p, light.clone()).expect("Unable to insert light");
+            }
         }
 
         particle_builder.requests.clear();
@@ -0,0 +1,139 @@
+use rltk::{Algorithm2D, BaseMap, DijkstraMap, Point};
+use std::collections::HashMap;
+
+use super::{Map, TileType, terrain::terrain};
+
+/// Nothing is ever further away than this on a flow field.
+const MAX_FLOW_DEPTH: f32 = 1000.0;
+
+/// The eight tiles around a spot, as offsets.
+pub const NEIGHBOURS: [(i32, i32); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+
+/// Somewhere worth walking to. `Travel`, `DownStairs` and `Unexplored` are the player's own
+/// goals, worked out from what the player knows of the map.
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
+pub enum Goal {
+    Player { x: i32, y: i32 },
+    Towards { x: i32, y: i32 },
+    Travel { x: i32, y: i32 },
+    DownStairs,
+    Unexplored
+}
+
+impl Goal {
+    /// The player opens a closed door by walking into it, so their paths can run through one.
+    fn through_doors(&self) -> bool {
+        matches!(self, Goal::Travel{ .. } | Goal::DownStairs | Goal::Unexplored)
+    }
+}
+
+/// The map as the player walks it: a closed door they know about and can open is no obstacle.
+struct ThroughDoors<'a> {
+    map: &'a Map
+}
+
+impl<'a> Algorithm2D for ThroughDoors<'a> {
+    fn dimensions(&self) -> Point {
+        self.map.dimensions()
+    }
+}
+
+impl<'a> BaseMap for ThroughDoors<'a> {
+    fn get_available_exits(&self, idx: usize) -> rltk::SmallVec<[(usize, f32); 10]> {
+        self.map.exits_where(idx, |i| passable(self.map, i, true))
+    }
+}
+
+/// Dijkstra maps towards common goals, worked out the first time something asks for
+/// one and shared by everyone until the map is re-indexed.
+#[derive(Default)]
+pub struct FlowFields {
+    fields: HashMap<Goal, Vec<f32>>
+}
+
+impl FlowFields {
+    pub fn new() -> FlowFields {
+        FlowFields { fields: HashMap::new() }
+    }
+
+    /// Throws every field away. Called whenever blockers may have moved.
+    pub fn invalidate(&mut self) {
+        self.fields.clear();
+    }
+
+    /// The open neighbouring tile that gets closest to the goal, if any of them get closer.
+    pub fn next_step(&mut self, map: &Map, goal: Goal, x: i32, y: i32) -> Option<(i32, i32)> {
+        let field = self.field(map, goal);
+        let mut best: Option<(i32, i32)> = None;
+        let mut best_distance = distance_at(map, field, x, y);
+        for (nx, ny) in open_neighbours(map, x, y, goal.through_doors()) {
+            let distance = field[map.xy_idx(nx, ny)];
+            if distance < best_distance {
+                best_distance = distance;
+                best = Some((nx, ny));
+            }
+        }
+        best
+    }
+
+    /// The open neighbouring tile that gets furthest from the goal, if any of them do.
+    pub fn flee_step(&mut self, map: &Map, goal: Goal, x: i32, y: i32) -> Option<(i32, i32)> {
+        let field = self.field(map, goal);
+        let mut best: Option<(i32, i32)> = None;
+        let mut best_distance = distance_at(map, field, x, y);
+        for (nx, ny) in open_neighbours(map, x, y, goal.through_doors()) {
+            let distance = field[map.xy_idx(nx, ny)];
+            if distance < f32::MAX && distance > best_distance {
+                best_distance = distance;
+                best = Some((nx, ny));
+            }
+        }
+        best
+    }
+
+    fn field(&mut self, map: &Map, goal: Goal) -> &[f32] {
+        self.fields.entry(goal).or_insert_with(|| build_field(map, goal))
+    }
+}
+
+fn build_field(map: &Map, goal: Goal) -> Vec<f32> {
+    let starts: Vec<usize> = match goal {
+        Goal::Player { x, y } | Goal::Towards { x, y } | Goal::Travel { x, y } => vec![map.xy_idx(x, y)],
+        Goal::DownStairs => (0..map.tiles.len())
+            .filter(|idx| map.tiles[*idx] == TileType::DownStairs && map.revealed_tiles[*idx])
+            .collect(),
+        Goal::Unexplored => (0..map.tiles.len())
+            .filter(|idx| !map.revealed_tiles[*idx] && terrain(map.tiles[*idx]).walkable)
+            .collect()
+    };
+    if goal.through_doors() {
+        DijkstraMap::new(map.width, map.height, &starts, &ThroughDoors{ map }, MAX_FLOW_DEPTH).map
+    } else {
+        DijkstraMap::new(map.width, map.height, &starts, map, MAX_FLOW_DEPTH).map
+    }
+}
+
+fn passable(map: &Map, idx: usize, through_doors: bool) -> bool {
+    !map.blocked[idx] || (through_doors && map.revealed_tiles[idx] && map.openable_doors.contains(&idx))
+}
+
+fn open_neighbours(map: &Map, x: i32, y: i32, through_doors: bool) -> Vec<(i32, i32)> {
+    NEIGHBOURS.iter()
+        .map(|(dx, dy)| (x + dx, y + dy))
+        .filter(|(nx, ny)| *nx >= 0 && *nx < map.width && *ny >= 0 && *ny < map.height)
+        .filter(|(nx, ny)| passable(map, map.xy_idx(*nx, *ny), through_doors))
+        .collect()
+}
+
+/// How far a tile is along a Dijkstra field. Whoever stands on a tile blocks it, so the
+/// field never reaches it directly; it is one step further than the best of its neighbours.
+pub fn distance_at(map: &Map, field: &[f32], x: i32, y: i32) -> f32 {
+    let own = field[map.xy_idx(x, y)];
+    if own < f32::MAX { return own; }
+    NEIGHBOURS.iter()
+        .map(|(dx, dy)| (x + dx, y + dy))
+        .filter(|(nx, ny)| *nx >= 0 && *nx < map.width && *ny >= 0 && *ny < map.height)
+        .map(|(nx, ny)| field[map.xy_idx(nx, ny)])
+        .filter(|distance| *distance < f32::MAX)
+        .fold(f32::MAX, |best, distance| f32::min(best, distance + 1.0))
+}
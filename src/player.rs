@@ -2,42 +2,183 @@ use rltk::{Point, Rltk, VirtualKeyCode};
 use specs::prelude::*;
 use std::cmp::{min, max};
 
-use super::{CombatStats, EntityMoved, game_log::GameLog, HungerClock, HungerState, Item, Map, Monster, Position, Player, RunState, State,
-            TileType, Viewshed, WantsToMelee, WantsToPickupItem};
+use super::{auto_action::{self, AutoAction, AutoPilot, RepeatCommand}, BlocksTile, BlocksVisibility, camera::Viewport, CombatStats, Confusion, Dialogue, DiggingTool, Door, EntityMoved, Equipped, EntryTrigger, game_log::{GameLog, LogCategory, LogEntry}, glyph_index::DOOR_OPEN_GLYPH,
+            gui::VendorMode, Hidden, HungerClock, HungerState, InBackpack, input::{self, Input}, Item, Key, keymap::{Action, Context}, LockedDoor, Map, Monster, Name, noise_system::*, Position, Player, Renderable, RunState, Slowed,
+            Sneaking, State, TileType, Vendor, Viewshed, WantsToMelee, WantsToPickupItem};
 
-fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
+/// A confused player only goes where they meant to half of the time.
+fn confused_direction(ecs: &World, delta_x: i32, delta_y: i32) -> (i32, i32) {
+    let player_entity = ecs.fetch::<Entity>();
+    if ecs.read_storage::<Confusion>().get(*player_entity).is_none() { return (delta_x, delta_y); }
+
+    let mut rng = ecs.write_resource::<rltk::RandomNumberGenerator>();
+    if rng.roll_dice(1, 2) == 1 { return (delta_x, delta_y); }
+
+    ecs.fetch_mut::<GameLog>().push(LogCategory::General, "You stumble around in confusion.");
+    loop {
+        let stumble_x = rng.range(-1, 2);
+        let stumble_y = rng.range(-1, 2);
+        if stumble_x != 0 || stumble_y != 0 { return (stumble_x, stumble_y); }
+    }
+}
+
+/// Opens a closed door the player bumped into, using up a matching key if it is locked.
+fn open_door(ecs: &World, map: &mut Map, doors: &mut WriteStorage<Door>, player: Entity, door: Entity, door_idx: usize) -> RunState {
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+    let mut locks = ecs.write_storage::<LockedDoor>();
+
+    if let Some(key_id) = locks.get(door).map(|lock| lock.key_id) {
+        let entities = ecs.entities();
+        let keys = ecs.read_storage::<Key>();
+        let backpack = ecs.read_storage::<InBackpack>();
+        let key = (&entities, &keys, &backpack).join()
+            .find(|(_, key, pack)| pack.owner == player && key.key_id == key_id)
+            .map(|(entity, _, _)| entity);
+
+        match key {
+            None => {
+                gamelog.push(LogCategory::General, "The door is locked. There must be a key somewhere.");
+                return RunState::AwaitingInput;
+            }
+            Some(key) => {
+                gamelog.push(LogCategory::General, "You unlock the door, and leave the key in the lock.");
+                entities.delete(key).expect("Unable to delete key");
+                locks.remove(door);
+            }
+        }
+    }
+
+    if let Some(door_state) = doors.get_mut(door) {
+        door_state.open = true;
+    }
+    if let Some(render) = ecs.write_storage::<Renderable>().get_mut(door) {
+        render.glyph = rltk::to_cp437(DOOR_OPEN_GLYPH);
+    }
+    ecs.write_storage::<BlocksTile>().remove(door);
+    ecs.write_storage::<BlocksVisibility>().remove(door);
+    map.blocked[door_idx] = false;
+    map.view_blocked.remove(&door_idx);
+    map.openable_doors.remove(&door_idx);
+    let (x, y) = (door_idx as i32 % map.width, door_idx as i32 / map.width);
+    ecs.fetch_mut::<NoiseBuilder>().make_noise(x, y, DOOR_NOISE);
+
+    RunState::PlayerTurn
+}
+
+/// The digging power of whatever tool the entity has equipped, if any.
+fn digging_power(ecs: &World, owner: Entity) -> Option<i32> {
+    let equipped = ecs.read_storage::<Equipped>();
+    let tools = ecs.read_storage::<DiggingTool>();
+    (&equipped, &tools).join()
+        .find(|(equipped, _)| equipped.owner == owner)
+        .map(|(_, tool)| tool.power)
+}
+
+pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) -> RunState {
+    let (delta_x, delta_y) = confused_direction(ecs, delta_x, delta_y);
     let mut positions = ecs.write_storage::<Position>();
     let mut players = ecs.write_storage::<Player>();
     let mut viewsheds = ecs.write_storage::<Viewshed>();
     let combat_stats = ecs.read_storage::<CombatStats>();
-    let map = ecs.fetch::<Map>();
+    let vendors = ecs.read_storage::<Vendor>();
+    let dialogues = ecs.read_storage::<Dialogue>();
+    let names = ecs.read_storage::<Name>();
+    let mut doors = ecs.write_storage::<Door>();
+    let sneaking = ecs.read_storage::<Sneaking>();
+    let mut slowed = ecs.write_storage::<Slowed>();
+    let mut hidden = ecs.write_storage::<Hidden>();
+    let mut map = ecs.fetch_mut::<Map>();
     let entities = ecs.entities();
     let mut wants_to_melee = ecs.write_storage::<WantsToMelee>();
     let mut entity_moved = ecs.write_storage::<EntityMoved>();
 
     for (entity, _player, pos, viewshed) in (&entities, &mut players, &mut positions, &mut viewsheds).join() {
-        if pos.x + delta_x < 1 || pos.x + delta_x > map.width - 1 || pos.y + delta_y < 1 || pos.y + delta_y > map.height - 1 { return ;}
+        if pos.x + delta_x < 1 || pos.x + delta_x > map.width - 1 || pos.y + delta_y < 1 || pos.y + delta_y > map.height - 1 { return RunState::PlayerTurn; }
         let dest_idx = map.xy_idx(pos.x + delta_x, pos.y + delta_y);
 
+        let mut closed_door: Option<Entity> = None;
+        let mut secret_door: Option<Entity> = None;
         for potential_target in map.tile_content[dest_idx].iter() {
+            if vendors.get(*potential_target).is_some() {
+                return RunState::ShowVendor { vendor: *potential_target, mode: VendorMode::Buy, selection: 0 };
+            }
+
+            if let Some(dialogue) = dialogues.get(*potential_target) {
+                if !dialogue.lines.is_empty() {
+                    let mut rng = ecs.write_resource::<rltk::RandomNumberGenerator>();
+                    let line = &dialogue.lines[rng.roll_dice(1, dialogue.lines.len() as i32) as usize - 1];
+                    let name = names.get(*potential_target).unwrap();
+                    let mut gamelog = ecs.fetch_mut::<GameLog>();
+                    gamelog.add(LogEntry::new(LogCategory::General).name(&name.name).text(format!(" says: \"{}\"", line)));
+                }
+                return RunState::PlayerTurn;
+            }
+
             let target = combat_stats.get(*potential_target);
             if let Some(_target) = target {
                 wants_to_melee.insert(entity, WantsToMelee { target: *potential_target }).expect("Add target failed");
-                return;
+                return RunState::PlayerTurn;
+            }
+
+            if let Some(door) = doors.get(*potential_target) {
+                if hidden.get(*potential_target).is_some() {
+                    secret_door = Some(*potential_target);
+                } else if !door.open {
+                    closed_door = Some(*potential_target);
+                }
+            }
+        }
+
+        // Feeling along a wall can turn up a secret door
+        if let Some(door) = secret_door {
+            hidden.remove(door);
+            map.tiles[dest_idx] = TileType::Floor;
+            ecs.fetch_mut::<GameLog>().push(LogCategory::General, "You find a secret door!");
+            return RunState::PlayerTurn;
+        }
+
+        if let Some(door) = closed_door {
+            viewshed.dirty = true;
+            return open_door(ecs, &mut map, &mut doors, entity, door, dest_idx);
+        }
+
+        if map.is_diggable(pos.x + delta_x, pos.y + delta_y) {
+            if let Some(power) = digging_power(ecs, entity) {
+                let mut gamelog = ecs.fetch_mut::<GameLog>();
+                ecs.fetch_mut::<NoiseBuilder>().make_noise(pos.x + delta_x, pos.y + delta_y, DIGGING_NOISE);
+                if map.dig(pos.x + delta_x, pos.y + delta_y, power) {
+                    gamelog.push(LogCategory::General, "The wall crumbles away.");
+                    viewshed.dirty = true;
+                } else {
+                    gamelog.push(LogCategory::General, "You hack at the wall.");
+                }
+                return RunState::PlayerTurn;
             }
         }
 
         if !map.blocked[dest_idx] {
-            pos.x = min(79, max(0, pos.x + delta_x));
-            pos.y = min(79, max(0, pos.y + delta_y));
+            pos.x = min(map.width - 1, max(0, pos.x + delta_x));
+            pos.y = min(map.height - 1, max(0, pos.y + delta_y));
             entity_moved.insert(entity, EntityMoved{}).expect("Unable to insert marker");
 
+            // Creeping along is quieter, but takes twice as long; running is louder
+            let running = matches!(ecs.fetch::<AutoPilot>().action, Some(AutoAction::Run{ .. }));
+            if sneaking.get(entity).is_some() {
+                ecs.fetch_mut::<NoiseBuilder>().make_noise(pos.x, pos.y, SNEAKING_NOISE);
+                slowed.insert(entity, Slowed{}).expect("Unable to insert slowed");
+            } else if running {
+                ecs.fetch_mut::<NoiseBuilder>().make_noise(pos.x, pos.y, RUNNING_NOISE);
+            } else {
+                ecs.fetch_mut::<NoiseBuilder>().make_noise(pos.x, pos.y, FOOTSTEP_NOISE);
+            }
+
             viewshed.dirty = true;
             let mut ppos = ecs.write_resource::<Point>();
             ppos.x = pos.x;
             ppos.y = pos.y;
         }
     }
+    RunState::PlayerTurn
 }
 
 pub fn try_next_level(ecs: &mut World) -> bool {
@@ -48,12 +189,12 @@ pub fn try_next_level(ecs: &mut World) -> bool {
         true
     } else {
         let mut gamelog = ecs.fetch_mut::<GameLog>();
-        gamelog.entries.push("There is no way down from here.".to_string());
+        gamelog.push(LogCategory::General, "There is no way down from here.");
         false
     }
 }
 
-fn get_item(ecs: &mut World) {
+pub fn get_item(ecs: &mut World) {
     let player_pos = ecs.fetch::<Point>();
     let player_entity = ecs.fetch::<Entity>();
     let entities = ecs.entities();
@@ -69,7 +210,7 @@ fn get_item(ecs: &mut World) {
     }
 
     match target_item {
-        None => gamelog.entries.push("There is nothing here to pick up.".to_string()),
+        None => gamelog.push(LogCategory::Item, "There is nothing here to pick up."),
         Some(item) => {
             let mut pickup = ecs.write_storage::<WantsToPickupItem>();
             pickup.insert(*player_entity, WantsToPickupItem { collected_by: *player_entity, item }).expect("Unable to insert want to pickup");
@@ -77,7 +218,123 @@ fn get_item(ecs: &mut World) {
     }
 }
 
-fn skip_turn(ecs: &mut World) -> RunState {
+/// Sneaking halves the player's speed but keeps their footsteps down.
+fn toggle_sneaking(ecs: &mut World) -> RunState {
+    let player_entity = *ecs.fetch::<Entity>();
+    let mut sneaking = ecs.write_storage::<Sneaking>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+    if sneaking.remove(player_entity).is_some() {
+        gamelog.push(LogCategory::General, "You stop sneaking.");
+    } else {
+        sneaking.insert(player_entity, Sneaking{}).expect("Unable to insert sneaking");
+        gamelog.push(LogCategory::General, "You start sneaking.");
+    }
+    RunState::AwaitingInput
+}
+
+/// Takes a turn to look carefully for hidden things, with much better odds than spotting them in passing.
+pub fn search(ecs: &mut World) -> RunState {
+    let player_entity = ecs.fetch::<Entity>();
+    let player_pos = ecs.fetch::<Point>();
+    let viewsheds = ecs.read_storage::<Viewshed>();
+    let names = ecs.read_storage::<Name>();
+    let mut hidden = ecs.write_storage::<Hidden>();
+    let doors = ecs.read_storage::<Door>();
+    let mut map = ecs.fetch_mut::<Map>();
+    let mut rng = ecs.write_resource::<rltk::RandomNumberGenerator>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+
+    let mut found_something = false;
+    let mut found_doors: Vec<usize> = Vec::new();
+    if let Some(viewshed) = viewsheds.get(*player_entity) {
+        for tile in viewshed.visible_tiles.iter() {
+            let idx = map.xy_idx(tile.x, tile.y);
+            let distance = rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, *tile);
+            let odds = if distance < 1.5 { 2 } else { 4 };
+            for e in map.tile_content[idx].iter() {
+                if hidden.get(*e).is_some() && rng.roll_dice(1, odds) == 1 {
+                    if let Some(name) = names.get(*e) {
+                        gamelog.push(LogCategory::Danger, format!("You spotted a {}.", &name.name));
+                    }
+                    hidden.remove(*e);
+                    if doors.get(*e).is_some() { found_doors.push(idx); }
+                    found_something = true;
+                }
+            }
+        }
+    }
+
+    // A secret door stops looking like wall once it has been found
+    for idx in found_doors {
+        map.tiles[idx] = TileType::Floor;
+    }
+
+    if !found_something {
+        gamelog.push(LogCategory::General, "You search the area, but find nothing.");
+    }
+
+    RunState::PlayerTurn
+}
+
+/// Tries to disarm a known trap next to the player. A botched attempt sends the player stumbling onto it.
+fn disarm_trap(ecs: &mut World) -> RunState {
+    let player_entity = ecs.fetch::<Entity>();
+    let mut player_pos = ecs.fetch_mut::<Point>();
+    let entities = ecs.entities();
+    let traps = ecs.read_storage::<EntryTrigger>();
+    let hidden = ecs.read_storage::<Hidden>();
+    let names = ecs.read_storage::<Name>();
+    let mut positions = ecs.write_storage::<Position>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+
+    let mut target: Option<(Entity, i32, i32)> = None;
+    for (trap, _trap, _hidden, pos) in (&entities, &traps, !&hidden, &positions).join() {
+        let distance = rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, Point::new(pos.x, pos.y));
+        if distance < 1.5 {
+            target = Some((trap, pos.x, pos.y));
+        }
+    }
+
+    let (trap, trap_x, trap_y) = match target {
+        None => {
+            gamelog.push(LogCategory::General, "There is no trap within reach to disarm.");
+            return RunState::AwaitingInput;
+        }
+        Some(target) => target
+    };
+    let trap_name = names.get(trap).map(|n| n.name.clone()).unwrap_or_else(|| "trap".to_string());
+
+    let roll = ecs.write_resource::<rltk::RandomNumberGenerator>().roll_dice(1, 6);
+    // A fumble can't carry the player onto a trap something else is standing on
+    let trap_occupied = {
+        let map = ecs.fetch::<Map>();
+        map.blocked[map.xy_idx(trap_x, trap_y)]
+    };
+    match roll {
+        1 if !trap_occupied => {
+            gamelog.push(LogCategory::Danger, format!("You fumble and stumble onto the {}!", trap_name));
+            if let Some(pos) = positions.get_mut(*player_entity) {
+                pos.x = trap_x;
+                pos.y = trap_y;
+            }
+            player_pos.x = trap_x;
+            player_pos.y = trap_y;
+            ecs.write_storage::<EntityMoved>().insert(*player_entity, EntityMoved{}).expect("Unable to insert marker");
+            if let Some(viewshed) = ecs.write_storage::<Viewshed>().get_mut(*player_entity) {
+                viewshed.dirty = true;
+            }
+        }
+        1..=3 => gamelog.push(LogCategory::General, format!("You fail to disarm the {}.", trap_name)),
+        _ => {
+            gamelog.push(LogCategory::General, format!("You carefully disarm the {}.", trap_name));
+            entities.delete(trap).expect("Unable to delete trap");
+        }
+    }
+
+    RunState::PlayerTurn
+}
+
+pub fn skip_turn(ecs: &mut World) -> RunState {
     let player_entity = ecs.fetch::<Entity>();
     let viewshed_components = ecs.read_storage::<Viewshed>();
     let monsters = ecs.read_storage::<Monster>();
@@ -117,64 +374,139 @@ fn skip_turn(ecs: &mut World) -> RunState {
 }
 
 pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
-    // Player movement
-    match ctx.key {
-        None => { return RunState::AwaitingInput } // Nothing happened
-        Some(key) => match key {
-            
-            // Card
-            VirtualKeyCode::Left |
-            VirtualKeyCode::Numpad4 |
-            VirtualKeyCode::H => try_move_player(-1, 0, &mut gs.ecs),
-            
-            VirtualKeyCode::Right |
-            VirtualKeyCode::Numpad6 |
-            VirtualKeyCode::L => try_move_player(1, 0, &mut gs.ecs),
-
-            VirtualKeyCode::Up |
-            VirtualKeyCode::Numpad8 |
-            VirtualKeyCode::K => try_move_player(0, -1, &mut gs.ecs),
-            
-            VirtualKeyCode::Down |
-            VirtualKeyCode::Numpad2 |
-            VirtualKeyCode::J => try_move_player(0, 1, &mut gs.ecs),
-
-            // Diagonals
-            VirtualKeyCode::Numpad9 |
-            VirtualKeyCode::Y => try_move_player(1, -1, &mut gs.ecs),
-
-            VirtualKeyCode::Numpad7 |
-            VirtualKeyCode::U => try_move_player(-1, -1, &mut gs.ecs),
-
-            VirtualKeyCode::Numpad3 |
-            VirtualKeyCode::N => try_move_player(1, 1, &mut gs.ecs),
-
-            VirtualKeyCode::Numpad1 |
-            VirtualKeyCode::B => try_move_player(-1, 1, &mut gs.ecs),
-
-            // Skip turn
-            VirtualKeyCode::Numpad5 => return skip_turn(&mut gs.ecs),
-            VirtualKeyCode::Space => return skip_turn(&mut gs.ecs),
-
-            // Items (Picking, Dropping, show menus, ...)
-            VirtualKeyCode::G => get_item(&mut gs.ecs),
-
-            VirtualKeyCode::I => return RunState::ShowInventory,
-            VirtualKeyCode::D => return RunState::ShowDropItem,
-            VirtualKeyCode::R => return RunState::ShowRemoveItem,
-
-            // Level changes
-            VirtualKeyCode::Period => {
-                if try_next_level(&mut gs.ecs) {
-                    return RunState::NextLevel;
-                }
+    // Difficult terrain costs the player their next turn
+    let player_entity = *gs.ecs.fetch::<Entity>();
+    if gs.ecs.write_storage::<Slowed>().remove(player_entity).is_some() {
+        return RunState::PlayerTurn;
+    }
+
+    // Keep exploring or travelling until something interrupts, or the player does
+    if gs.ecs.fetch::<AutoPilot>().action.is_some() {
+        if gs.ecs.fetch::<Input>().pressed() || ctx.left_click {
+            auto_action::stop(&mut gs.ecs);
+            return RunState::AwaitingInput;
+        }
+        return auto_action::continue_action(&mut gs.ecs);
+    }
+
+    // Click on a known tile to walk there
+    if ctx.left_click {
+        let (mouse_x, mouse_y) = ctx.mouse_pos();
+        let map = gs.ecs.fetch::<Map>();
+        let clicked = Viewport::new(&gs.ecs, ctx).to_world(&map, mouse_x, mouse_y)
+            .filter(|(x, y)| map.revealed_tiles[map.xy_idx(*x, *y)]);
+        drop(map);
+        if let Some((x, y)) = clicked {
+            return auto_action::start(&mut gs.ecs, AutoAction::Travel{ x, y });
+        }
+    }
+
+    let (key, shift) = {
+        let input = gs.ecs.fetch::<Input>();
+        if !input.pressed() { return RunState::AwaitingInput } // Nothing happened
+        (input.key, input.shift)
+    };
+
+    // Digits build up a count for the next command to repeat
+    if let Some(digit) = key.and_then(digit_key) {
+        gs.ecs.fetch_mut::<AutoPilot>().push_digit(digit);
+        return RunState::AwaitingInput;
+    }
+    let count = gs.ecs.fetch_mut::<AutoPilot>().take_count();
+
+    let action = match input::action(&gs.ecs, Context::Game) {
+        None => { return RunState::AwaitingInput }
+        Some(action) => action
+    };
+
+    // Player movement; holding shift runs
+    if let Some((delta_x, delta_y)) = action.direction() {
+        if shift && key.is_some() {
+            auto_action::start(&mut gs.ecs, AutoAction::Run{ dx: delta_x, dy: delta_y, openness: None });
+        } else if let Some(count) = count {
+            return repeat(&mut gs.ecs, RepeatCommand::Step{ dx: delta_x, dy: delta_y }, count);
+        }
+        return try_move_player(delta_x, delta_y, &mut gs.ecs);
+    }
+
+    if let Some(count) = count {
+        match action {
+            Action::Wait => return repeat(&mut gs.ecs, RepeatCommand::Rest, count),
+            Action::Search => return repeat(&mut gs.ecs, RepeatCommand::Search, count),
+            _ => {}
+        }
+    }
+
+    match action {
+        // Skip turn
+        Action::Wait => return skip_turn(&mut gs.ecs),
+
+        // Items (Picking, Dropping, show menus, ...)
+        Action::PickUp => get_item(&mut gs.ecs),
+
+        Action::Inventory => return RunState::ShowInventory { selection: 0 },
+        Action::Drop => return RunState::ShowDropItem { selection: 0 },
+        Action::Unequip => return RunState::ShowRemoveItem { selection: 0 },
+
+        // Traps
+        Action::Search => return search(&mut gs.ecs),
+        Action::Disarm => return disarm_trap(&mut gs.ecs),
+
+        // Stealth
+        Action::Sneak => return toggle_sneaking(&mut gs.ecs),
+
+        // Auto-explore
+        Action::Explore => return auto_action::start(&mut gs.ecs, AutoAction::Explore),
+
+        // Look around
+        Action::Examine => {
+            let player_pos = gs.ecs.fetch::<Point>();
+            return RunState::ShowExamine { x: player_pos.x, y: player_pos.y };
+        }
+
+        // Level changes
+        Action::TravelToStairs => return auto_action::travel_to_stairs(&mut gs.ecs),
+        Action::Descend => {
+            if try_next_level(&mut gs.ecs) {
+                return RunState::NextLevel;
             }
+        }
 
-            //Save and Quit
-            VirtualKeyCode::Escape => return RunState::SaveGame,
+        // Character sheet, overview map and message history
+        Action::CharacterSheet => return RunState::ShowCharacter,
+        Action::Overview => return RunState::ShowOverview,
+        Action::MessageLog => return RunState::ShowLog { offset: 0, filter: None },
 
-            _ => { return RunState::AwaitingInput }
-        },
+        // Controls
+        Action::Keybindings => return RunState::ShowKeybindings { selection: 0, waiting: false },
+
+        //Save and Quit
+        Action::SaveAndQuit => return RunState::SaveGame,
+
+        _ => { return RunState::AwaitingInput }
     }
     RunState::PlayerTurn
+}
+
+fn digit_key(key: VirtualKeyCode) -> Option<i32> {
+    match key {
+        VirtualKeyCode::Key0 => Some(0),
+        VirtualKeyCode::Key1 => Some(1),
+        VirtualKeyCode::Key2 => Some(2),
+        VirtualKeyCode::Key3 => Some(3),
+        VirtualKeyCode::Key4 => Some(4),
+        VirtualKeyCode::Key5 => Some(5),
+        VirtualKeyCode::Key6 => Some(6),
+        VirtualKeyCode::Key7 => Some(7),
+        VirtualKeyCode::Key8 => Some(8),
+        VirtualKeyCode::Key9 => Some(9),
+        _ => None
+    }
+}
+
+/// Does the first of a counted run of commands now, and leaves the rest to the autopilot.
+fn repeat(ecs: &mut World, command: RepeatCommand, count: i32) -> RunState {
+    let state = auto_action::start(ecs, AutoAction::Repeat{ command, remaining: count });
+    if ecs.fetch::<AutoPilot>().action.is_none() { return state; }
+    auto_action::continue_action(ecs)
 }
\ No newline at end of file
@@ -0,0 +1,10 @@
+use serde::{Serialize, Deserialize};
+
+/// Running totals for the current game, shown on the victory screen.
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct RunStats {
+    pub turns: i32,
+    pub kills: i32,
+    pub deepest_depth: i32,
+    pub bosses_killed: Vec<String>
+}
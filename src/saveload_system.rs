@@ -28,9 +28,12 @@ pub fn save_game(_ecs: &mut World) {
 pub fn save_game(ecs: &mut World) {
     // Create helper
     let mapcopy = ecs.get_mut::<super::map::Map>().unwrap().clone();
+    let statscopy = ecs.get_mut::<super::run_stats::RunStats>().unwrap().clone();
+    let logcopy = ecs.get_mut::<super::game_log::GameLog>().unwrap().clone();
+    let settingscopy = ecs.get_mut::<super::settings::GameSettings>().unwrap().clone();
     let savehelper = ecs
         .create_entity()
-        .with(SerializationHelper{map: mapcopy})
+        .with(SerializationHelper{map: mapcopy, run_stats: statscopy, log: logcopy, settings: settingscopy})
         .marked::<SimpleMarker<SerializeMe>>()
         .build();
 
@@ -41,11 +44,12 @@ pub fn save_game(ecs: &mut World) {
         let writer = File::create("./savegame.json").unwrap();
         let mut serializer = serde_json::Serializer::new(writer);
         serialize_individually!(ecs, serializer, data, Position, Renderable, Player, Viewshed, Monster,
-            Name, BlocksTile, CombatStats, SufferDamage, WantsToMelee, Item, Consumable, Ranged, InflictsDamage,
+            Name, Description, BlocksTile, CombatStats, SufferDamage, WantsToMelee, Item, Consumable, Ranged, InflictsDamage,
             AreaOfEffect, Confusion, ProvidesHealing, InBackpack, WantsToPickupItem, WantsToUseItem,
             WantsToDropItem, SerializationHelper, Equippable, Equipped, MeleePowerBonus, DefenseBonus,
             WantsToRemoveItem, ParticleLifetime, HungerClock, ProvidesFood, MagicMapper, Hidden, EntryTrigger,
-            EntityMoved, SingleActivation
+            EntityMoved, SingleActivation, ItemValue, Wallet, GoldPile, Vendor, Npc, Dialogue, McGuffin, Boss,
+            TeleportsVictim, Alarm, Pit, InflictsPoison, InflictsConfusion, Poisoned, Asleep, Door, BlocksVisibility, LockedDoor, Key, Slowed, DiggingTool, LightSource, Investigating, Sneaking, LastSeen
         );
     }
 
@@ -91,11 +95,12 @@ pub fn load_game(ecs: &mut World) {
         let mut d = (&mut ecs.entities(), &mut ecs.write_storage::<SimpleMarker<SerializeMe>>(), &mut ecs.write_resource::<SimpleMarkerAllocator<SerializeMe>>());
 
         deserialize_individually!(ecs, de, d, Position, Renderable, Player, Viewshed, Monster,
-            Name, BlocksTile, CombatStats, SufferDamage, WantsToMelee, Item, Consumable, Ranged, InflictsDamage,
+            Name, Description, BlocksTile, CombatStats, SufferDamage, WantsToMelee, Item, Consumable, Ranged, InflictsDamage,
             AreaOfEffect, Confusion, ProvidesHealing, InBackpack, WantsToPickupItem, WantsToUseItem,
             WantsToDropItem, SerializationHelper, Equippable, Equipped, MeleePowerBonus, DefenseBonus,
             WantsToRemoveItem, ParticleLifetime, HungerClock, ProvidesFood, MagicMapper, Hidden, EntryTrigger,
-            EntityMoved, SingleActivation
+            EntityMoved, SingleActivation, ItemValue, Wallet, GoldPile, Vendor, Npc, Dialogue, McGuffin, Boss,
+            TeleportsVictim, Alarm, Pit, InflictsPoison, InflictsConfusion, Poisoned, Asleep, Door, BlocksVisibility, LockedDoor, Key, Slowed, DiggingTool, LightSource, Investigating, Sneaking, LastSeen
         );
     }
 
@@ -108,7 +113,15 @@ pub fn load_game(ecs: &mut World) {
         for (e, h) in (&entities, &helper).join() {
             let mut worldmap = ecs.write_resource::<super::map::Map>();
             *worldmap = h.map.clone();
-            worldmap.tile_content = vec![Vec::new(); super::map::MAPCOUNT];
+            worldmap.reset_transient();
+            ecs.write_resource::<super::pathfinding::FlowFields>().invalidate();
+            ecs.write_resource::<super::auto_action::AutoPilot>().action = None;
+            ecs.write_resource::<super::targeting::LastTarget>().entity = None;
+            let mut run_stats = ecs.write_resource::<super::run_stats::RunStats>();
+            *run_stats = h.run_stats.clone();
+            let mut gamelog = ecs.write_resource::<super::game_log::GameLog>();
+            *gamelog = h.log.clone();
+            *ecs.write_resource::<super::settings::GameSettings>() = h.settings.clone();
             deleteme = Some(e);
         }
         for (e, _p, pos) in (&entities, &player, &position).join() {
@@ -0,0 +1,27 @@
+use rltk::Point;
+use specs::prelude::*;
+
+use super::{map::SCENT_STRENGTH, Map, RunState};
+
+/// Lays down the player's scent and lets the old trail fade, leaving a trail for
+/// monsters that have lost sight of the player.
+pub struct ScentSystem {}
+
+impl<'a> System<'a> for ScentSystem {
+    type SystemData = ( WriteExpect<'a, Map>,
+                        ReadExpect<'a, Point>,
+                        ReadExpect<'a, RunState>
+                      );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut map, player_pos, runstate) = data;
+
+        if *runstate != RunState::MonsterTurn { return; }
+
+        for scent in map.scent.iter_mut() {
+            *scent = i32::max(0, *scent - 1);
+        }
+        let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+        map.scent[player_idx] = SCENT_STRENGTH;
+    }
+}
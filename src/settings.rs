@@ -0,0 +1,38 @@
+use serde::{Serialize, Deserialize};
+use std::fs;
+use std::path::Path;
+
+const SETTINGS_FILE: &str = "./settings.json";
+
+/// The depth the McGuffin is found on when the settings file doesn't say otherwise.
+const DEFAULT_FINAL_DEPTH: i32 = 10;
+
+/// The shallowest final depth allowed; the boss lair on depth 5 has to come first.
+const MIN_FINAL_DEPTH: i32 = 6;
+
+/// Choices that shape a run. Read from the settings file when a game starts, then saved
+/// with the game so a loaded run keeps the rules it began with.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct GameSettings {
+    /// The depth the McGuffin is found on. There are no stairs any further down.
+    pub final_depth: i32
+}
+
+impl Default for GameSettings {
+    fn default() -> GameSettings {
+        GameSettings { final_depth: DEFAULT_FINAL_DEPTH }
+    }
+}
+
+impl GameSettings {
+    /// The settings from the config file, or the defaults if there isn't a usable one.
+    pub fn load() -> GameSettings {
+        if !Path::new(SETTINGS_FILE).exists() { return GameSettings::default(); }
+        let mut settings: GameSettings = match fs::read_to_string(SETTINGS_FILE).ok().and_then(|data| serde_json::from_str(&data).ok()) {
+            None => return GameSettings::default(),
+            Some(settings) => settings
+        };
+        settings.final_depth = i32::max(MIN_FINAL_DEPTH, settings.final_depth);
+        settings
+    }
+}
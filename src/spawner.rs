@@ -3,9 +3,9 @@ use specs::prelude::*;
 use specs::saveload::{MarkedBuilder, SimpleMarker};
 use std::collections::HashMap;
 
-use super::{ AreaOfEffect, BlocksTile, colors::*, CombatStats, Confusion, Consumable, DefenseBonus, EquipmentSlot, Equippable, EntryTrigger,
-             glyph_index::*, Hidden, HungerClock, HungerState, InflictsDamage, Item, MagicMapper, map::MAPWIDTH, MeleePowerBonus, Monster, Name, Player,
-             Position, ProvidesFood, ProvidesHealing, random_tables::RandomTable, Ranged, rect::Rect, Renderable, SerializeMe, Viewshed };
+use super::{ Alarm, AreaOfEffect, Asleep, BlocksTile, BlocksVisibility, Boss, BossAbility, colors::*, CombatStats, Confusion, Consumable, DefenseBonus, Description, Dialogue, DiggingTool, Door, EquipmentSlot, Equippable, EntryTrigger,
+             glyph_index::*, GoldPile, Hidden, HungerClock, HungerState, InBackpack, InflictsConfusion, InflictsDamage, InflictsPoison, Item, ItemValue, Key, LightSource, LockedDoor, MagicMapper, map::Map, McGuffin, MeleePowerBonus, Monster, Name, Npc, Pit, Player,
+             Position, ProvidesFood, ProvidesHealing, random_tables::RandomTable, Ranged, rect::Rect, Renderable, SerializeMe, settings::GameSettings, SingleActivation, TeleportsVictim, terrain::terrain, TileType, Vendor, Viewshed, Wallet };
 
 /// Spawn the player and returns his/her entity object.
 pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
@@ -28,6 +28,7 @@ pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
             dirty: true
         })
         .with(Name{ name: "Player".to_string() })
+        .with(Description{ description: "That's you, the seeker of the McGuffin.".to_string() })
         .with(CombatStats {
             max_hp: 30,
             hp: 30,
@@ -35,13 +36,15 @@ pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
             power: 5
         })
         .with(HungerClock{ state: HungerState::WellFed, duration: 20})
+        .with(Wallet{ gold: 0 })
+        .with(LightSource{ color: LANTERN_LIGHT, range: 6 })
         .marked::<SimpleMarker<SerializeMe>>()
         .build()
 }
 
 const MAX_MONSTERS: i32 = 4;
 
-fn room_table(map_depth: i32) -> RandomTable {
+fn room_table(map_depth: i32, final_depth: i32) -> RandomTable {
     RandomTable::new() 
         .add("Goblin", 10)
         .add("Orc", 1 + map_depth)
@@ -53,18 +56,44 @@ fn room_table(map_depth: i32) -> RandomTable {
         .add("shield", 3)
         .add("Longsword", map_depth - 1)
         .add("Tower Shield", map_depth - 1) 
+        .add("Pickaxe", 1)
         .add("Rations", 10)
         .add("Magic Mapping Scroll", 2)
         .add("Bear Trap", 2)
+        .add("Teleport Trap", 1 + map_depth / 3)
+        .add("Alarm Trap", 1 + map_depth / 3)
+        .add("Pit Trap", if map_depth < final_depth { map_depth / 2 } else { 0 })
+        .add("Poison Dart Trap", map_depth / 2)
+        .add("Gas Trap", map_depth / 3)
+        .add("Gold", 6)
+}
+
+fn merchant_table(map_depth: i32) -> RandomTable {
+    RandomTable::new()
+        .add("Health Potion", 10)
+        .add("Rations", 8)
+        .add("Magic Missile Scroll", 4 + map_depth)
+        .add("Fireball Scroll", map_depth)
+        .add("Confusion Scroll", 2 + map_depth / 2)
+        .add("Magic Mapping Scroll", 3)
+        .add("Pickaxe", 2)
+        .add("Dagger", 4)
+        .add("Shield", 4)
+        .add("Longsword", map_depth - 1)
+        .add("Tower Shield", map_depth - 1)
 }
 
 pub fn spawn_room(ecs: &mut World, room: &Rect, map_depth: i32) {
-    let spawn_table = room_table(map_depth);
+    let final_depth = ecs.fetch::<GameSettings>().final_depth;
+    let spawn_table = room_table(map_depth, final_depth);
     let mut spawn_points: HashMap<usize, String> = HashMap::new();
+    let map_width;
 
     // Scope to keep borrow checker happy
     {
         let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        let map = ecs.fetch::<Map>();
+        map_width = map.width as usize;
         let num_spawns = rng.roll_dice(1, MAX_MONSTERS + 3) +(map_depth - 1) - 3;
 
         for _i in 0..num_spawns {
@@ -72,11 +101,11 @@ pub fn spawn_room(ecs: &mut World, room: &Rect, map_depth: i32) {
             let mut tries = 0;
 
             while !added && tries < 20 {
-                let x = (room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1))) as usize;
-                let y = (room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1))) as usize;
+                let x = room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1));
+                let y = room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1));
 
-                let idx = (y * MAPWIDTH) + x;
-                if !spawn_points.contains_key(&idx) {
+                let idx = map.xy_idx(x, y);
+                if map.tiles[idx] == TileType::Floor && !spawn_points.contains_key(&idx) {
                     spawn_points.insert(idx, spawn_table.roll(&mut rng));
                     added = true;
                 } else {
@@ -88,25 +117,38 @@ pub fn spawn_room(ecs: &mut World, room: &Rect, map_depth: i32) {
 
     // Actually spawn the monster
     for spawn in spawn_points.iter() {
-        let x = (*spawn.0 % MAPWIDTH) as i32;
-        let y = (*spawn.0 / MAPWIDTH) as i32;
-
-        match spawn.1.as_ref() {
-            "Goblin" => goblin(ecs, x, y),
-            "Orc" => orc(ecs, x, y),
-            "Health Potion" => health_potion(ecs, x, y),
-            "Fireball Scroll" => fireball_scroll(ecs, x, y),
-            "Confusion Scroll" => confusion_scroll(ecs, x, y),
-            "Magic Missile Scroll" => magic_missile_scrolls(ecs, x, y),
-            "Dagger" => dagger(ecs, x, y),
-            "Shield" => shield(ecs, x, y),
-            "Longsword" => longsword(ecs, x, y),
-            "Tower Shield" => tower_shield(ecs, x, y),
-            "Rations" => rations(ecs, x, y),
-            "Magic Mapping Scroll" => magic_mapping_scroll(ecs, x, y),
-            "Bear Trap" => bear_trap(ecs, x, y),
-            _ => {}
-        }
+        let x = (*spawn.0 % map_width) as i32;
+        let y = (*spawn.0 / map_width) as i32;
+
+        spawn_named(ecs, spawn.1, x, y);
+    }
+}
+
+/// Spawns an entity by its table name. Items hand back their entity so they
+/// can be moved straight into a backpack.
+pub fn spawn_named(ecs: &mut World, name: &str, x: i32, y: i32) -> Option<Entity> {
+    match name {
+        "Goblin" => { goblin(ecs, x, y); None }
+        "Orc" => { orc(ecs, x, y); None }
+        "Health Potion" => Some(health_potion(ecs, x, y)),
+        "Fireball Scroll" => Some(fireball_scroll(ecs, x, y)),
+        "Confusion Scroll" => Some(confusion_scroll(ecs, x, y)),
+        "Magic Missile Scroll" => Some(magic_missile_scrolls(ecs, x, y)),
+        "Dagger" => Some(dagger(ecs, x, y)),
+        "Shield" => Some(shield(ecs, x, y)),
+        "Longsword" => Some(longsword(ecs, x, y)),
+        "Tower Shield" => Some(tower_shield(ecs, x, y)),
+        "Rations" => Some(rations(ecs, x, y)),
+        "Magic Mapping Scroll" => Some(magic_mapping_scroll(ecs, x, y)),
+        "Pickaxe" => Some(pickaxe(ecs, x, y)),
+        "Bear Trap" => Some(bear_trap(ecs, x, y)),
+        "Teleport Trap" => Some(teleport_trap(ecs, x, y)),
+        "Alarm Trap" => Some(alarm_trap(ecs, x, y)),
+        "Pit Trap" => Some(pit_trap(ecs, x, y)),
+        "Poison Dart Trap" => Some(poison_dart_trap(ecs, x, y)),
+        "Gas Trap" => Some(gas_trap(ecs, x, y)),
+        "Gold" => Some(gold(ecs, x, y)),
+        _ => None
     }
 }
 
@@ -114,20 +156,27 @@ fn orc(ecs: &mut World, x: i32, y: i32) {
     let glyph: u16 = rltk::to_cp437(ORC_GLYPH);
     let fg: RGB = return_rgb(ORC_FG);
     let name: &str = "Orc";
+    let description: &str = "A hulking brute with a short temper and a shorter attention span.";
 
-    monster(ecs, x, y, glyph, fg, name);
+    monster(ecs, x, y, glyph, fg, name, description);
 }
 
 fn goblin(ecs: &mut World, x: i32, y: i32) {
     let glyph: u16 = rltk::to_cp437(GOBLIN_GLYPH);
     let fg: RGB = return_rgb(GOBLIN_FG);
     let name: &str = "Goblin";
+    let description: &str = "A wiry little thief that would gladly stab you for your boots.";
 
-    monster(ecs, x, y, glyph, fg, name);
+    monster(ecs, x, y, glyph, fg, name, description);
 }
 
-fn monster<S: ToString>(ecs: &mut World, x: i32, y: i32, glyph: rltk::FontCharType, fg: RGB, name: S) {
-    ecs.create_entity()
+fn monster<S: ToString>(ecs: &mut World, x: i32, y: i32, glyph: rltk::FontCharType, fg: RGB, name: S, description: S) {
+    let (gold, asleep) = {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        (rng.roll_dice(1, 6) - 1, rng.roll_dice(1, 4) == 1)
+    };
+
+    let mut builder = ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
             glyph,
@@ -142,6 +191,7 @@ fn monster<S: ToString>(ecs: &mut World, x: i32, y: i32, glyph: rltk::FontCharTy
         })
         .with(Monster{})
         .with(Name{ name: name.to_string() })
+        .with(Description{ description: description.to_string() })
         .with(BlocksTile{})
         .with(CombatStats{
             max_hp: 16,
@@ -149,11 +199,16 @@ fn monster<S: ToString>(ecs: &mut World, x: i32, y: i32, glyph: rltk::FontCharTy
             defense: 1,
             power: 4
         })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .with(Wallet{ gold });
+
+    if asleep {
+        builder = builder.with(Asleep{});
+    }
+
+    builder.marked::<SimpleMarker<SerializeMe>>().build();
 }
 
-fn health_potion(ecs: &mut World, x: i32, y: i32) {
+fn health_potion(ecs: &mut World, x: i32, y: i32) -> Entity {
     let glyph: u16 = rltk::to_cp437(POTION_GLYPH);
     let fg: RGB = return_rgb(HEALTH_POT_FG);
     let bg: RGB = return_rgb(DEFAULT_BG);
@@ -168,14 +223,16 @@ fn health_potion(ecs: &mut World, x: i32, y: i32) {
             render_order: 2
         })
         .with(Name { name: name.to_string() })
+        .with(Description{ description: "A bubbling red draught. Drinking it knits wounds closed.".to_string() })
         .with(Item{})
+        .with(ItemValue{ value: 20 })
         .with(Consumable{})
         .with(ProvidesHealing { heal_amount: 8})
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
 }
 
-fn magic_missile_scrolls(ecs: &mut World, x: i32, y: i32) {
+fn magic_missile_scrolls(ecs: &mut World, x: i32, y: i32) -> Entity {
     let glyph: u16 = rltk::to_cp437(SCROLL_GLYPH);
     let fg: RGB = return_rgb(MMS_FG);
     let bg: RGB = return_rgb(DEFAULT_BG);
@@ -189,15 +246,17 @@ fn magic_missile_scrolls(ecs: &mut World, x: i32, y: i32) {
             render_order: 2
         })
         .with(Name{ name: "Magic Missile Scroll".to_string() })
+        .with(Description{ description: "Reading it hurls a bolt of force at a single target.".to_string() })
         .with(Item{})
+        .with(ItemValue{ value: 25 })
         .with(Consumable{})
         .with(Ranged{ range: 6 })
         .with(InflictsDamage{ damage: 8 })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
 }
 
-fn fireball_scroll(ecs: &mut World, x: i32, y: i32) {
+fn fireball_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
 
     let glyph: u16 = rltk::to_cp437(SCROLL_GLYPH);
     let fg: RGB = return_rgb(FIREBALL_FG);
@@ -212,16 +271,18 @@ fn fireball_scroll(ecs: &mut World, x: i32, y: i32) {
             render_order: 2
         })
         .with(Name{ name: "Fireball Scroll".to_string()})
+        .with(Description{ description: "Reading it engulfs an area in roaring flame.".to_string() })
         .with(Item{})
+        .with(ItemValue{ value: 40 })
         .with(Consumable{})
         .with(Ranged{range: 6})
         .with(InflictsDamage{damage: 20})
         .with(AreaOfEffect{radius: 3})
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
 }
 
-fn confusion_scroll(ecs: &mut World, x: i32, y: i32) {
+fn confusion_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
 
     let glyph: u16 = rltk::to_cp437(SCROLL_GLYPH);
     let fg: RGB = return_rgb(CONFUSION_FG);
@@ -236,15 +297,17 @@ fn confusion_scroll(ecs: &mut World, x: i32, y: i32) {
             render_order: 2
         })
         .with(Name{ name: "Confusion Scroll".to_string() })
+        .with(Description{ description: "Reading it addles the mind of a single target.".to_string() })
         .with(Item{})
+        .with(ItemValue{ value: 30 })
         .with(Consumable{})
         .with(Ranged{range: 6})
         .with(Confusion{ turns: 4 })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
 }
 
-fn dagger(ecs: &mut World, x: i32, y: i32) {
+fn dagger(ecs: &mut World, x: i32, y: i32) -> Entity {
     let glyph: u16 = rltk::to_cp437(DAGGER_GLYPH);
     let fg: RGB = return_rgb(DAGGER_FG);
     let bg: RGB = return_rgb(DEFAULT_BG);
@@ -258,14 +321,16 @@ fn dagger(ecs: &mut World, x: i32, y: i32) {
             render_order: 2
         })
         .with(Name{ name: name.to_string() })
+        .with(Description{ description: "A short, sharp blade. Better than your fists.".to_string() })
         .with(Item{})
+        .with(ItemValue{ value: 15 })
         .with(Equippable{ slot: EquipmentSlot::Melee })
         .with(MeleePowerBonus{ power: 2 })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
 }
 
-fn shield(ecs: &mut World, x: i32, y: i32) {
+fn shield(ecs: &mut World, x: i32, y: i32) -> Entity {
     let glyph: u16 = rltk::to_cp437(SHIELD_GLYPH);
     let fg: RGB = return_rgb(SHIELD_FG);
     let bg: RGB = return_rgb(DEFAULT_BG);
@@ -280,14 +345,16 @@ fn shield(ecs: &mut World, x: i32, y: i32) {
             render_order: 2
         })
         .with(Name { name: name.to_string() })
+        .with(Description{ description: "A small wooden shield, good for turning aside blows.".to_string() })
         .with(Item {})
+        .with(ItemValue{ value: 15 })
         .with(Equippable{ slot: EquipmentSlot::Shield })
         .with(DefenseBonus{ defense: 1})
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
 }
 
-fn longsword(ecs: &mut World, x: i32, y: i32) {
+fn longsword(ecs: &mut World, x: i32, y: i32) -> Entity {
     
     let glyph: u16 = rltk::to_cp437(SWORD_GLYPH);
     let fg: RGB = return_rgb(SWORD_FG);
@@ -303,14 +370,42 @@ fn longsword(ecs: &mut World, x: i32, y: i32) {
             render_order: 2
         })
         .with(Name { name: name.to_string()})
+        .with(Description{ description: "A well balanced blade with a long reach.".to_string() })
         .with(Item{})
+        .with(ItemValue{ value: 50 })
         .with(Equippable{ slot: EquipmentSlot::Melee })
         .with(MeleePowerBonus{ power: 4})
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
 }
 
-fn tower_shield(ecs: &mut World, x: i32, y: i32) {
+fn pickaxe(ecs: &mut World, x: i32, y: i32) -> Entity {
+
+    let glyph: u16 = rltk::to_cp437(PICKAXE_GLYPH);
+    let fg: RGB = return_rgb(PICKAXE_FG);
+    let bg: RGB = return_rgb(DEFAULT_BG);
+    let name: &str = "Pickaxe";
+
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph,
+            fg,
+            bg,
+            render_order: 2
+        })
+        .with(Name { name: name.to_string()})
+        .with(Description{ description: "A miner's pick. Wield it and walk into a wall to dig through.".to_string() })
+        .with(Item{})
+        .with(ItemValue{ value: 30 })
+        .with(Equippable{ slot: EquipmentSlot::Melee })
+        .with(MeleePowerBonus{ power: 1 })
+        .with(DiggingTool{ power: 1 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn tower_shield(ecs: &mut World, x: i32, y: i32) -> Entity {
 
     let glyph: u16 = rltk::to_cp437(TOWER_S_GLYPH);
     let fg: RGB = return_rgb(TOWER_S_FG);
@@ -326,14 +421,16 @@ fn tower_shield(ecs: &mut World, x: i32, y: i32) {
             render_order: 2
         })
         .with(Name { name: name.to_string()})
+        .with(Description{ description: "A heavy slab of iron that covers you from chin to toe.".to_string() })
         .with(Item{})
+        .with(ItemValue{ value: 50 })
         .with(Equippable{ slot: EquipmentSlot::Shield })
         .with(DefenseBonus{ defense: 3})
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
 }
 
-fn rations(ecs: &mut World, x: i32, y: i32) {
+fn rations(ecs: &mut World, x: i32, y: i32) -> Entity {
 
     let glyph: u16 = rltk::to_cp437(RATIONS_GLYPH);
     let fg: RGB = return_rgb(RATION_FG);
@@ -349,14 +446,16 @@ fn rations(ecs: &mut World, x: i32, y: i32) {
             render_order: 2
         })
         .with(Name{ name: name.to_string() })
+        .with(Description{ description: "Dried meat and hard bread. Not tasty, but filling.".to_string() })
         .with(Item{})
+        .with(ItemValue{ value: 5 })
         .with(ProvidesFood{})
         .with(Consumable{})
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
 }
 
-fn magic_mapping_scroll(ecs: &mut World, x: i32, y: i32) {
+fn magic_mapping_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
     
     let glyph: u16 = rltk::to_cp437(SCROLL_GLYPH);
     let fg: RGB = return_rgb(MAGICMAP_FG);
@@ -372,19 +471,231 @@ fn magic_mapping_scroll(ecs: &mut World, x: i32, y: i32) {
             render_order: 2
         })
         .with(Name{ name: name.to_string() })
+        .with(Description{ description: "Reading it reveals the layout of the current level.".to_string() })
         .with(Item{})
+        .with(ItemValue{ value: 35 })
         .with(MagicMapper{})
         .with(Consumable{})
         .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+/// Starts building a hidden trap; callers add the trap's effect before building it.
+fn trap<'a>(ecs: &'a mut World, x: i32, y: i32, fg: (f32, f32, f32), name: &str, description: &str) -> EntityBuilder<'a> {
+    ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph: rltk::to_cp437(TRAP_GLYPH),
+            fg: return_rgb(fg),
+            bg: return_rgb(DEFAULT_BG),
+            render_order: 2
+        })
+        .with(Name{ name: name.to_string() })
+        .with(Description{ description: description.to_string() })
+        .with(Hidden{})
+        .with(EntryTrigger{})
+}
+
+fn bear_trap(ecs: &mut World, x: i32, y: i32) -> Entity {
+    trap(ecs, x, y, BEARTRAP_FG, "Bear Trap", "A spring loaded jaw of rusty iron teeth.")
+        .with(InflictsDamage{ damage: 6 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn teleport_trap(ecs: &mut World, x: i32, y: i32) -> Entity {
+    trap(ecs, x, y, TELEPORT_TRAP_FG, "Teleport Trap", "A faintly glowing rune that flings whoever steps on it elsewhere.")
+        .with(TeleportsVictim{})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn alarm_trap(ecs: &mut World, x: i32, y: i32) -> Entity {
+    trap(ecs, x, y, ALARM_TRAP_FG, "Alarm Trap", "A tripwire strung to a cluster of bells.")
+        .with(Alarm{ radius: 12 })
+        .with(SingleActivation{})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn pit_trap(ecs: &mut World, x: i32, y: i32) -> Entity {
+    trap(ecs, x, y, PIT_TRAP_FG, "Pit Trap", "A thin layer of boards over a long drop to the level below.")
+        .with(Pit{})
+        .with(InflictsDamage{ damage: 3 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn poison_dart_trap(ecs: &mut World, x: i32, y: i32) -> Entity {
+    trap(ecs, x, y, POISON_TRAP_FG, "Poison Dart Trap", "A pressure plate that fires a dart tipped with something green.")
+        .with(InflictsDamage{ damage: 1 })
+        .with(InflictsPoison{ damage: 1, turns: 8 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn gas_trap(ecs: &mut World, x: i32, y: i32) -> Entity {
+    trap(ecs, x, y, GAS_TRAP_FG, "Gas Trap", "A vent that hisses out a cloud of dizzying gas.")
+        .with(InflictsConfusion{ turns: 4 })
+        .with(SingleActivation{})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn gold(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let amount = {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        let depth = ecs.fetch::<Map>().depth;
+        rng.roll_dice(2, 6) + depth * 2
+    };
+    gold_pile(ecs, x, y, amount)
+}
+
+/// Spawns a pile of `amount` gold coins, used for floor loot and monster drops.
+pub fn gold_pile(ecs: &mut World, x: i32, y: i32, amount: i32) -> Entity {
+
+    let glyph: u16 = rltk::to_cp437(GOLD_GLYPH);
+    let fg: RGB = return_rgb(GOLD_FG);
+    let bg: RGB = return_rgb(DEFAULT_BG);
+    let name: &str = "Gold";
+
+    ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph,
+            fg,
+            bg,
+            render_order: 2
+        })
+        .with(Name{ name: name.to_string() })
+        .with(Description{ description: format!("A pile of {} gold coins.", amount) })
+        .with(Item{})
+        .with(GoldPile{ amount })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+const MERCHANT_STOCK: i32 = 6;
+
+/// Gives each level a one in three chance of a merchant setting up shop in one of its rooms.
+pub fn maybe_spawn_merchant(ecs: &mut World, map: &Map) {
+    if map.rooms.len() < 3 { return; }
+
+    let room = {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        if rng.roll_dice(1, 3) != 1 { return; }
+        let room_idx = rng.roll_dice(1, map.rooms.len() as i32 - 2) as usize;
+        map.rooms[room_idx]
+    };
+
+    // Tuck the merchant into a free corner of the room, out of the way of the corridors,
+    // or failing that anywhere in the room they can stand
+    let spot = {
+        let positions = ecs.read_storage::<Position>();
+        let corners = [(room.x1 + 1, room.y1 + 1), (room.x2, room.y1 + 1), (room.x1 + 1, room.y2), (room.x2, room.y2)];
+        let anywhere = (room.y1 + 1 ..= room.y2).flat_map(|y| (room.x1 + 1 ..= room.x2).map(move |x| (x, y)));
+        corners.iter().copied().chain(anywhere).find(|(x, y)| {
+            let idx = map.xy_idx(*x, *y);
+            terrain(map.tiles[idx]).walkable && !map.blocked[idx] && !positions.join().any(|pos| pos.x == *x && pos.y == *y)
+        })
+    };
+    if let Some((x, y)) = spot {
+        vendor(ecs, x, y, "Merchant", "A travelling trader, somehow doing business this deep underground.", map.depth);
+    }
+}
+
+fn vendor(ecs: &mut World, x: i32, y: i32, name: &str, description: &str, map_depth: i32) {
+
+    let glyph: u16 = rltk::to_cp437(MERCHANT_GLYPH);
+    let fg: RGB = return_rgb(MERCHANT_FG);
+    let bg: RGB = return_rgb(DEFAULT_BG);
+
+    let vendor = ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph,
+            fg,
+            bg,
+            render_order: 1
+        })
+        .with(Name{ name: name.to_string() })
+        .with(Description{ description: description.to_string() })
+        .with(BlocksTile{})
+        .with(Vendor{})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+
+    // Stock the vendor's backpack
+    let stock_table = merchant_table(map_depth);
+    for _i in 0..MERCHANT_STOCK {
+        let roll = {
+            let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+            stock_table.roll(&mut rng)
+        };
+        if let Some(item) = spawn_named(ecs, &roll, x, y) {
+            ecs.write_storage::<Position>().remove(item);
+            ecs.write_storage::<InBackpack>().insert(item, InBackpack{ owner: vendor }).expect("Unable to stock vendor");
+        }
+    }
+}
+
+/// Fills the town with a shopkeeper in the first building and townsfolk wandering the streets.
+pub fn spawn_town(ecs: &mut World, map: &Map) {
+    if let Some(shop) = map.rooms.get(1) {
+        let (x, y) = shop.center();
+        vendor(ecs, x, y, "Shopkeeper", "The town's only shopkeeper, who insists every price is a bargain.", 1);
+    }
+
+    let (square_x, square_y) = map.rooms[0].center();
+    villager(ecs, square_x - 3, square_y - 2, "Old Timer", "A wrinkled local who has seen many adventurers go down and few come back.",
+             &["Nobody's ever come back up with the McGuffin.", "My grandfather went down there. Still waiting on him.", "Mind the traps, youngster."]);
+    villager(ecs, square_x + 3, square_y + 2, "Town Guard", "A bored guard leaning on a rusty halberd.",
+             &["Move along.", "The entrance to the dungeon is east of the square.", "No trouble, now."]);
+
+    // Everyone else is at home
+    let townsfolk = [
+        ("Farmer", "A farmer with dirt under every fingernail.", "The harvest was poor again this year."),
+        ("Baker", "A baker dusted head to toe in flour.", "Fresh bread! Well, yesterday's bread."),
+        ("Child", "A grubby child who is supposed to be at lessons.", "Are you going to be a hero? You don't look like one.")
+    ];
+    for (i, (name, description, line)) in townsfolk.iter().enumerate() {
+        if let Some(house) = map.rooms.get(i + 2) {
+            let (x, y) = house.center();
+            villager(ecs, x, y, name, description, &[*line]);
+        }
+    }
+}
+
+fn villager(ecs: &mut World, x: i32, y: i32, name: &str, description: &str, lines: &[&str]) {
+
+    let glyph: u16 = rltk::to_cp437(VILLAGER_GLYPH);
+    let fg: RGB = return_rgb(VILLAGER_FG);
+    let bg: RGB = return_rgb(DEFAULT_BG);
+
+    ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph,
+            fg,
+            bg,
+            render_order: 1
+        })
+        .with(Name{ name: name.to_string() })
+        .with(Description{ description: description.to_string() })
+        .with(BlocksTile{})
+        .with(Npc{})
+        .with(Dialogue{ lines: lines.iter().map(|line| line.to_string()).collect() })
+        .marked::<SimpleMarker<SerializeMe>>()
         .build();
 }
 
-fn bear_trap(ecs: &mut World, x: i32, y: i32) {
+/// Spawns the object of the quest. Picking it up wins the game.
+pub fn mcguffin(ecs: &mut World, x: i32, y: i32) {
 
-    let glyph: u16 = rltk::to_cp437(BEARTRAP_GLYPH);
-    let fg: RGB = return_rgb(BEARTRAP_FG);
+    let glyph: u16 = rltk::to_cp437(MCGUFFIN_GLYPH);
+    let fg: RGB = return_rgb(MCGUFFIN_FG);
     let bg: RGB = return_rgb(DEFAULT_BG);
-    let name: &str = "Bear Trap";
+    let name: &str = "McGuffin";
 
     ecs.create_entity()
         .with(Position{ x, y })
@@ -395,9 +706,222 @@ fn bear_trap(ecs: &mut World, x: i32, y: i32) {
             render_order: 2
         })
         .with(Name{ name: name.to_string() })
+        .with(Description{ description: "The fabled McGuffin. Nobody is quite sure what it does, but everybody wants it.".to_string() })
+        .with(Item{})
+        .with(McGuffin{})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+}
+
+fn vault_loot_table(map_depth: i32) -> RandomTable {
+    RandomTable::new()
+        .add("Health Potion", 5)
+        .add("Fireball Scroll", 2 + map_depth / 2)
+        .add("Confusion Scroll", 3)
+        .add("Magic Missile Scroll", 3)
+        .add("Magic Mapping Scroll", 2)
+        .add("Longsword", 2)
+        .add("Tower Shield", 2)
+}
+
+/// Spawns the contents of a vault from the markers left by `vaults::stamp_vault`.
+pub fn spawn_vault(ecs: &mut World, spawns: &[(i32, i32, char)], map_depth: i32) {
+    let loot_table = vault_loot_table(map_depth);
+
+    for (x, y, glyph) in spawns.iter() {
+        let (x, y) = (*x, *y);
+        match glyph {
+            '!' => {
+                // Vault loot is guaranteed, so keep rolling until the table gives us something
+                let mut roll = "None".to_string();
+                {
+                    let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+                    while roll == "None" {
+                        roll = loot_table.roll(&mut rng);
+                    }
+                }
+                spawn_named(ecs, &roll, x, y);
+            }
+            '$' => { gold(ecs, x, y); }
+            'g' => goblin(ecs, x, y),
+            'o' => orc(ecs, x, y),
+            'W' => goblin_warlord(ecs, x, y),
+            'O' => orc_overlord(ecs, x, y),
+            'M' => mcguffin(ecs, x, y),
+            '+' => door(ecs, x, y),
+            'S' => secret_door(ecs, x, y),
+            'L' => {
+                // The key is left lying in one of the level's ordinary rooms
+                locked_door(ecs, x, y, map_depth);
+                let (key_x, key_y) = {
+                    let map = ecs.fetch::<Map>();
+                    let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+                    let mut spot = map.rooms[0].center();
+                    for _ in 0..100 {
+                        let room = &map.rooms[rng.range(0, map.rooms.len() as i32) as usize];
+                        let (x, y) = (rng.range(room.x1 + 1, room.x2 + 1), rng.range(room.y1 + 1, room.y2 + 1));
+                        if map.tiles[map.xy_idx(x, y)] == TileType::Floor {
+                            spot = (x, y);
+                            break;
+                        }
+                    }
+                    spot
+                };
+                vault_key(ecs, key_x, key_y, map_depth);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn goblin_warlord(ecs: &mut World, x: i32, y: i32) {
+    let glyph: u16 = rltk::to_cp437(WARLORD_GLYPH);
+    let fg: RGB = return_rgb(WARLORD_FG);
+    let stats = CombatStats{ max_hp: 40, hp: 40, defense: 2, power: 7 };
+    let boss_info = Boss{
+        ability: BossAbility::Regenerate,
+        ability_active: false,
+        death_message: "The Goblin Warlord collapses, and his war drums fall silent.".to_string()
+    };
+
+    boss(ecs, x, y, glyph, fg, "Goblin Warlord", "A scarred goblin chieftain whose wounds close almost as fast as you open them.", stats, boss_info);
+}
+
+fn orc_overlord(ecs: &mut World, x: i32, y: i32) {
+    let glyph: u16 = rltk::to_cp437(OVERLORD_GLYPH);
+    let fg: RGB = return_rgb(OVERLORD_FG);
+    let stats = CombatStats{ max_hp: 60, hp: 60, defense: 3, power: 9 };
+    let boss_info = Boss{
+        ability: BossAbility::Enrage,
+        ability_active: false,
+        death_message: "The Orc Overlord topples from his throne. The McGuffin is unguarded!".to_string()
+    };
+
+    boss(ecs, x, y, glyph, fg, "Orc Overlord", "The hulking master of the deep, who flies into a rage when wounded.", stats, boss_info);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn boss(ecs: &mut World, x: i32, y: i32, glyph: rltk::FontCharType, fg: RGB, name: &str, description: &str, stats: CombatStats, boss_info: Boss) {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph,
+            fg,
+            bg: return_rgb(DEFAULT_BG),
+            render_order: 1
+        })
+        .with(Viewshed {
+            visible_tiles: Vec::new(),
+            range: 8,
+            dirty: true
+        })
+        .with(Monster{})
+        .with(Name{ name: name.to_string() })
+        .with(Description{ description: description.to_string() })
+        .with(BlocksTile{})
+        .with(stats)
+        .with(boss_info)
+        .with(Wallet{ gold: 50 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+}
+
+/// Hangs plain doors in roughly half of the level's doorways.
+pub fn spawn_doors(ecs: &mut World, map: &Map) {
+    for (x, y) in map.doorways() {
+        let roll = ecs.write_resource::<RandomNumberGenerator>().roll_dice(1, 2);
+        if roll == 1 {
+            door(ecs, x, y);
+        }
+    }
+}
+
+/// Starts building a closed door; callers add any lock or secrecy before building it.
+fn door_builder<'a>(ecs: &'a mut World, x: i32, y: i32, fg: (f32, f32, f32), name: &str, description: &str) -> EntityBuilder<'a> {
+    ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph: rltk::to_cp437(DOOR_CLOSED_GLYPH),
+            fg: return_rgb(fg),
+            bg: return_rgb(DEFAULT_BG),
+            render_order: 2
+        })
+        .with(Name{ name: name.to_string() })
+        .with(Description{ description: description.to_string() })
+        .with(Door{ open: false })
+        .with(BlocksTile{})
+        .with(BlocksVisibility{})
+}
+
+fn door(ecs: &mut World, x: i32, y: i32) {
+    door_builder(ecs, x, y, DOOR_FG, "Door", "A heavy wooden door on rusted hinges.")
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+}
+
+fn locked_door(ecs: &mut World, x: i32, y: i32, key_id: i32) {
+    door_builder(ecs, x, y, LOCKED_DOOR_FG, "Locked Door", "An iron-banded door with a sturdy lock. Somewhere on this level is a key for it.")
+        .with(LockedDoor{ key_id })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+}
+
+fn secret_door(ecs: &mut World, x: i32, y: i32) {
+    door_builder(ecs, x, y, DOOR_FG, "Secret Door", "A section of wall that swings aside on a hidden pivot.")
         .with(Hidden{})
-        .with(EntryTrigger{})
-        .with(InflictsDamage{ damage: 6 })
         .marked::<SimpleMarker<SerializeMe>>()
         .build();
-}
\ No newline at end of file
+}
+
+fn vault_key(ecs: &mut World, x: i32, y: i32, key_id: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph: rltk::to_cp437(KEY_GLYPH),
+            fg: return_rgb(KEY_FG),
+            bg: return_rgb(DEFAULT_BG),
+            render_order: 2
+        })
+        .with(Name{ name: "Vault Key".to_string() })
+        .with(Description{ description: "A heavy iron key. It should open a locked door on this level.".to_string() })
+        .with(Item{})
+        .with(Key{ key_id })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+/// Mounts torches in some rooms and lets glowing fungus grow in others.
+pub fn spawn_lights(ecs: &mut World, map: &Map) {
+    for room in map.rooms.iter() {
+        let (center_x, _) = room.center();
+        let (torch_roll, fungus_roll, fungus_x, fungus_y) = {
+            let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+            (rng.roll_dice(1, 2), rng.roll_dice(1, 5), rng.range(room.x1 + 1, room.x2 + 1), rng.range(room.y1 + 1, room.y2 + 1))
+        };
+
+        // Torches hang just inside the room's top wall
+        if torch_roll == 1 && map.tiles[map.xy_idx(center_x, room.y1 + 1)] == TileType::Floor {
+            light_fixture(ecs, center_x, room.y1 + 1, TORCH_GLYPH, TORCH_FG, TORCH_LIGHT, 6, "Torch", "A sputtering torch in an iron bracket.");
+        }
+        if fungus_roll == 1 && map.tiles[map.xy_idx(fungus_x, fungus_y)] == TileType::Floor {
+            light_fixture(ecs, fungus_x, fungus_y, FUNGUS_GLYPH, FUNGUS_FG, FUNGUS_LIGHT, 3, "Glowing Fungus", "A clump of mushrooms giving off a faint green glow.");
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn light_fixture(ecs: &mut World, x: i32, y: i32, glyph: char, fg: (f32, f32, f32), light: (f32, f32, f32), range: i32, name: &str, description: &str) {
+    ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph: rltk::to_cp437(glyph),
+            fg: return_rgb(fg),
+            bg: return_rgb(DEFAULT_BG),
+            render_order: 3
+        })
+        .with(Name{ name: name.to_string() })
+        .with(Description{ description: description.to_string() })
+        .with(LightSource{ color: light, range })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+}
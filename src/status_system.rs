@@ -0,0 +1,57 @@
+use specs::prelude::*;
+
+use super::{Confusion, game_log::{GameLog, LogCategory}, Poisoned, RunState, SufferDamage};
+
+pub struct StatusSystem {}
+
+impl<'a> System<'a> for StatusSystem {
+    type SystemData = ( Entities<'a>,
+                        ReadExpect<'a, Entity>,
+                        ReadExpect<'a, RunState>,
+                        WriteStorage<'a, Poisoned>,
+                        WriteStorage<'a, Confusion>,
+                        WriteStorage<'a, SufferDamage>,
+                        WriteExpect<'a, GameLog>
+                      );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, player_entity, runstate, mut poisoned, mut confusion, mut inflict_damage, mut log) = data;
+
+        // Statuses tick on their owner's turn, like hunger
+        let is_current_turn = |entity: Entity| match *runstate {
+            RunState::PlayerTurn => entity == *player_entity,
+            RunState::MonsterTurn => entity != *player_entity,
+            _ => false
+        };
+
+        let mut cured: Vec<Entity> = Vec::new();
+        for (entity, poison) in (&entities, &mut poisoned).join() {
+            if !is_current_turn(entity) { continue; }
+
+            SufferDamage::new_damage(&mut inflict_damage, entity, poison.damage);
+            poison.turns -= 1;
+            if poison.turns < 1 {
+                cured.push(entity);
+                if entity == *player_entity {
+                    log.push(LogCategory::General, "The poison wears off.");
+                }
+            }
+        }
+        for entity in cured {
+            poisoned.remove(entity);
+        }
+
+        // Monsters shake off confusion in their AI; the player does so here
+        if *runstate == RunState::PlayerTurn {
+            let mut clear_headed = false;
+            if let Some(confused) = confusion.get_mut(*player_entity) {
+                confused.turns -= 1;
+                clear_headed = confused.turns < 1;
+            }
+            if clear_headed {
+                confusion.remove(*player_entity);
+                log.push(LogCategory::General, "Your head clears.");
+            }
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use rltk::Point;
+use specs::prelude::*;
+
+use super::{Map, Monster, Position, Viewshed};
+
+/// The monster the player last fired at, so the next shot starts out aimed at it.
+#[derive(Default)]
+pub struct LastTarget {
+    pub entity: Option<Entity>
+}
+
+impl LastTarget {
+    pub fn new() -> LastTarget {
+        LastTarget { entity: None }
+    }
+}
+
+/// Tiles the player can see within range of a shot.
+pub fn tiles_in_range(ecs: &World, range: i32) -> Vec<Point> {
+    let player_entity = ecs.fetch::<Entity>();
+    let player_pos = ecs.fetch::<Point>();
+    let viewsheds = ecs.read_storage::<Viewshed>();
+    match viewsheds.get(*player_entity) {
+        None => Vec::new(),
+        Some(viewshed) => viewshed.visible_tiles.iter()
+            .filter(|tile| rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, **tile) <= range as f32)
+            .copied()
+            .collect()
+    }
+}
+
+/// Monsters standing on any of `tiles`, nearest to the player first.
+pub fn hostiles(ecs: &World, tiles: &[Point]) -> Vec<(Entity, Point)> {
+    let map = ecs.fetch::<Map>();
+    let player_pos = ecs.fetch::<Point>();
+    let monsters = ecs.read_storage::<Monster>();
+
+    let mut hostiles: Vec<(Entity, Point)> = Vec::new();
+    for tile in tiles.iter() {
+        for entity in map.tile_content[map.xy_idx(tile.x, tile.y)].iter() {
+            if monsters.get(*entity).is_some() { hostiles.push((*entity, *tile)); }
+        }
+    }
+    hostiles.sort_by(|a, b| {
+        let (distance_a, distance_b) = (rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, a.1), rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, b.1));
+        distance_a.partial_cmp(&distance_b).expect("Distances should never be NaN")
+    });
+    hostiles
+}
+
+/// Where the reticle starts: on the last monster fired at if it is still in sight, otherwise
+/// on the nearest monster, otherwise on the player.
+pub fn first_target(ecs: &World, hostiles: &[(Entity, Point)]) -> (i32, i32) {
+    let last = ecs.fetch::<LastTarget>().entity;
+    let aimed = hostiles.iter().find(|(entity, _)| Some(*entity) == last).or_else(|| hostiles.first());
+    match aimed {
+        Some((_, pos)) => (pos.x, pos.y),
+        None => {
+            let player_pos = ecs.fetch::<Point>();
+            (player_pos.x, player_pos.y)
+        }
+    }
+}
+
+/// Remembers whatever monster is standing where the player just fired.
+pub fn remember(ecs: &World, x: i32, y: i32) {
+    let monsters = ecs.read_storage::<Monster>();
+    let positions = ecs.read_storage::<Position>();
+    let entities = ecs.entities();
+    let target = (&entities, &monsters, &positions).join()
+        .find(|(_, _, pos)| pos.x == x && pos.y == y)
+        .map(|(entity, _, _)| entity);
+    if target.is_some() {
+        ecs.fetch_mut::<LastTarget>().entity = target;
+    }
+}
+
+/// The tiles a shot passes over on its way from the player to the target.
+pub fn projectile_path(from: Point, to: Point) -> Vec<Point> {
+    rltk::line2d(rltk::LineAlg::Bresenham, from, to).into_iter()
+        .filter(|tile| *tile != from && *tile != to)
+        .collect()
+}
@@ -0,0 +1,94 @@
+use super::colors::*;
+use super::glyph_index::*;
+use super::TileType;
+
+/// What happens to whoever steps onto a tile.
+#[derive(PartialEq, Copy, Clone)]
+pub enum OnEnter {
+    Nothing,
+    Burn(i32),
+    Fall,
+    Slow
+}
+
+/// Everything the game needs to know about a kind of tile. Walls ignore `glyph`
+/// and are drawn joined up to their neighbours instead.
+pub struct Terrain {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub walkable: bool,
+    pub opaque: bool,
+    pub cost: f32,
+    pub glyph: char,
+    pub fg: (f32, f32, f32),
+    pub bg: (f32, f32, f32),
+    pub on_enter: OnEnter
+}
+
+const WALL: Terrain = Terrain {
+    name: "Wall", description: "Cold, damp stone.",
+    walkable: false, opaque: true, cost: 1.0,
+    glyph: WALL_GLYPH, fg: WALL_COLOR, bg: DEFAULT_BG, on_enter: OnEnter::Nothing
+};
+
+const FLOOR: Terrain = Terrain {
+    name: "Floor", description: "Worn flagstones.",
+    walkable: true, opaque: false, cost: 1.0,
+    glyph: FLOOR_GLYPH, fg: FLOOR_COLOR, bg: DEFAULT_BG, on_enter: OnEnter::Nothing
+};
+
+const DOWN_STAIRS: Terrain = Terrain {
+    name: "Down Stairs", description: "A staircase leading deeper into the dungeon.",
+    walkable: true, opaque: false, cost: 1.0,
+    glyph: STAIRS_GLYPH, fg: STAIRS_FG, bg: DEFAULT_BG, on_enter: OnEnter::Nothing
+};
+
+const SHALLOW_WATER: Terrain = Terrain {
+    name: "Shallow Water", description: "Ankle deep, cold and murky.",
+    walkable: true, opaque: false, cost: 1.5,
+    glyph: SHALLOW_WATER_GLYPH, fg: WATER_FG, bg: DEFAULT_BG, on_enter: OnEnter::Nothing
+};
+
+const DEEP_WATER: Terrain = Terrain {
+    name: "Deep Water", description: "Dark water too deep to wade through.",
+    walkable: false, opaque: false, cost: 1.0,
+    glyph: DEEP_WATER_GLYPH, fg: DEEP_WATER_FG, bg: DEEP_WATER_BG, on_enter: OnEnter::Nothing
+};
+
+const LAVA: Terrain = Terrain {
+    name: "Lava", description: "Molten rock. Stepping in it would hurt. A lot.",
+    walkable: true, opaque: false, cost: 10.0,
+    glyph: LAVA_GLYPH, fg: LAVA_FG, bg: LAVA_BG, on_enter: OnEnter::Burn(5)
+};
+
+const CHASM: Terrain = Terrain {
+    name: "Chasm", description: "A yawning gap. You can't see the bottom, but it is probably the next level.",
+    walkable: true, opaque: false, cost: 20.0,
+    glyph: CHASM_GLYPH, fg: CHASM_FG, bg: DEFAULT_BG, on_enter: OnEnter::Fall
+};
+
+const TALL_GRASS: Terrain = Terrain {
+    name: "Tall Grass", description: "Pale grass growing higher than your head.",
+    walkable: true, opaque: true, cost: 1.0,
+    glyph: TALL_GRASS_GLYPH, fg: GRASS_FG, bg: DEFAULT_BG, on_enter: OnEnter::Nothing
+};
+
+const RUBBLE: Terrain = Terrain {
+    name: "Rubble", description: "Loose stones from a collapsed ceiling. Slow going.",
+    walkable: true, opaque: false, cost: 2.0,
+    glyph: RUBBLE_GLYPH, fg: RUBBLE_FG, bg: DEFAULT_BG, on_enter: OnEnter::Slow
+};
+
+pub fn terrain(tile: TileType) -> &'static Terrain {
+    match tile {
+        TileType::Wall => &WALL,
+        TileType::Floor => &FLOOR,
+        TileType::DownStairs => &DOWN_STAIRS,
+        TileType::ShallowWater => &SHALLOW_WATER,
+        TileType::DeepWater => &DEEP_WATER,
+        TileType::Lava => &LAVA,
+        TileType::Chasm => &CHASM,
+        TileType::TallGrass => &TALL_GRASS,
+        TileType::Rubble => &RUBBLE
+    }
+}
@@ -0,0 +1,72 @@
+use specs::prelude::*;
+
+use super::{colors::*, EntityMoved, game_log::{GameLog, LogCategory}, glyph_index::POW_GLYPH, Map, Name, particle_system::ParticleBuilder, Position, RunState,
+            settings::GameSettings, Slowed, SufferDamage, terrain::{terrain, OnEnter}};
+
+/// Applies the on-enter effect of whatever terrain a moving entity ended up on.
+/// Runs before the `TriggerSystem`, which clears the movement markers.
+pub struct TerrainSystem {}
+
+impl<'a> System<'a> for TerrainSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = ( WriteExpect<'a, Map>,
+                        Entities<'a>,
+                        ReadStorage<'a, EntityMoved>,
+                        ReadStorage<'a, Position>,
+                        ReadStorage<'a, Name>,
+                        ReadExpect<'a, Entity>,
+                        WriteExpect<'a, RunState>,
+                        WriteExpect<'a, GameLog>,
+                        WriteExpect<'a, ParticleBuilder>,
+                        WriteStorage<'a, SufferDamage>,
+                        WriteStorage<'a, Slowed>,
+                        ReadExpect<'a, GameSettings>
+                      );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut map, entities, entity_moved, position, names, player_entity, mut runstate,
+             mut log, mut particle_builder, mut inflict_damage, mut slowed, settings) = data;
+
+        for (entity, _moved, pos) in (&entities, &entity_moved, &position).join() {
+            let idx = map.xy_idx(pos.x, pos.y);
+            let tile_terrain = terrain(map.tiles[idx]);
+            let is_player = entity == *player_entity;
+            let visible = map.visible_tiles[idx];
+            let name = names.get(entity).map_or("something", |name| name.name.as_str());
+
+            match tile_terrain.on_enter {
+                OnEnter::Nothing => {}
+                OnEnter::Burn(damage) => {
+                    SufferDamage::new_damage(&mut inflict_damage, entity, damage);
+                    particle_builder.request(pos.x, pos.y, return_rgb(LAVA_FG), return_rgb(LAVA_BG), rltk::to_cp437(POW_GLYPH), 200.0);
+                    if is_player {
+                        log.push(LogCategory::Danger, format!("The {} burns you!", tile_terrain.name.to_lowercase()));
+                    } else if visible {
+                        log.push(LogCategory::Danger, format!("The {} burns the {}!", tile_terrain.name.to_lowercase(), name));
+                    }
+                }
+                OnEnter::Fall => {
+                    if is_player {
+                        if map.depth < settings.final_depth {
+                            log.push(LogCategory::Danger, "You tumble into the chasm and land hard on the level below!");
+                            SufferDamage::new_damage(&mut inflict_damage, entity, 3);
+                            *runstate = RunState::NextLevel;
+                        }
+                    } else {
+                        if visible {
+                            log.push(LogCategory::General, format!("The {} falls into the chasm.", name));
+                        }
+                        map.blocked[idx] = false;
+                        entities.delete(entity).expect("Unable to delete falling entity");
+                    }
+                }
+                OnEnter::Slow => {
+                    slowed.insert(entity, Slowed{}).expect("Unable to insert slowed");
+                    if is_player {
+                        log.push(LogCategory::General, format!("You pick your way through the {}.", tile_terrain.name.to_lowercase()));
+                    }
+                }
+            }
+        }
+    }
+}
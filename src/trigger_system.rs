@@ -1,13 +1,17 @@
+use rltk::{Point, RandomNumberGenerator};
 use specs::prelude::*;
 
-use super::{colors::*, EntityMoved, EntryTrigger, game_log::GameLog, glyph_index::POW_GLYPH, Hidden, InflictsDamage, Map, Name, particle_system::ParticleBuilder, Position, SingleActivation, SufferDamage };
+use super::{Alarm, Asleep, colors::*, Confusion, EntityMoved, EntryTrigger, game_log::{GameLog, LogCategory, LogEntry}, glyph_index::POW_GLYPH, Hidden, InflictsConfusion, InflictsDamage, InflictsPoison,
+            Map, Name, noise_system::{NoiseBuilder, TRAP_NOISE}, particle_system::ParticleBuilder, Pit, Poisoned, Position, RunState, settings::GameSettings, SingleActivation, SufferDamage, TeleportsVictim,
+            TileType, Viewshed };
 
 pub struct TriggerSystem {}
 
 impl<'a> System<'a> for TriggerSystem {
-    type SystemData = ( ReadExpect<'a, Map>,
+    #[allow(clippy::type_complexity)]
+    type SystemData = ( WriteExpect<'a, Map>,
                         WriteStorage<'a, EntityMoved>,
-                        ReadStorage<'a, Position>,
+                        WriteStorage<'a, Position>,
                         ReadStorage<'a, EntryTrigger>,
                         WriteStorage<'a, Hidden>,
                         ReadStorage<'a, Name>,
@@ -16,46 +20,107 @@ impl<'a> System<'a> for TriggerSystem {
                         ReadStorage<'a, InflictsDamage>,
                         WriteExpect<'a, ParticleBuilder>,
                         WriteStorage<'a, SufferDamage>,
-                        ReadStorage<'a, SingleActivation>
+                        ReadStorage<'a, SingleActivation>,
+                        ReadStorage<'a, TeleportsVictim>,
+                        ReadStorage<'a, Alarm>,
+                        ReadStorage<'a, Pit>,
+                        ReadStorage<'a, InflictsPoison>,
+                        WriteStorage<'a, Poisoned>,
+                        ReadStorage<'a, InflictsConfusion>,
+                        WriteStorage<'a, Confusion>,
+                        WriteStorage<'a, Asleep>,
+                        WriteStorage<'a, Viewshed>,
+                        ReadExpect<'a, Entity>,
+                        WriteExpect<'a, Point>,
+                        WriteExpect<'a, RunState>,
+                        WriteExpect<'a, RandomNumberGenerator>,
+                        WriteExpect<'a, NoiseBuilder>,
+                        ReadExpect<'a, GameSettings>
                        );
-    
+
     fn run(&mut self, data: Self::SystemData) {
-        let (map, mut entity_moved, position, entry_trigger, mut hidden, names, entities, mut log,
-             inflicts_damage, mut particle_builder, mut inflict_damage, single_activation) = data;
- 
-        // Iterate the entities that moved and their final position
+        let (mut map, mut entity_moved, mut position, entry_trigger, mut hidden, names, entities, mut log,
+             inflicts_damage, mut particle_builder, mut inflict_damage, single_activation,
+             teleports, alarms, pits, inflicts_poison, mut poisoned, inflicts_confusion, mut confusion, mut asleep,
+             mut viewsheds, player_entity, mut player_pos, mut runstate, mut rng, mut noise, settings) = data;
+
+        // Gather the entities that moved and their final position
+        let mut moved: Vec<(Entity, i32, i32)> = Vec::new();
+        for (entity, _entity_moved, pos) in (&entities, &entity_moved, &position).join() {
+            moved.push((entity, pos.x, pos.y));
+        }
+
         let mut remove_entities: Vec<Entity> = Vec::new();
-        for (entity, mut _entity_moved, pos) in (&entities, &mut entity_moved, &position).join() {
-            let idx = map.xy_idx(pos.x, pos.y);
-            for entity_id in map.tile_content[idx].iter() {
-                if entity != *entity_id { // Do not bother to check yourself for being a trap!
-                    let maybe_trigger = entry_trigger.get(*entity_id);
-                    match maybe_trigger {
-                        None => {},
-                        Some(_trigger) => {
-                            // We triggered it
-                            let name = names.get(*entity_id);
-                            if let Some(name) = name {
-                                log.entries.push(format!("{} triggers!", &name.name));
-                            }
+        let mut teleport_victims: Vec<Entity> = Vec::new();
+        let mut alarms_raised: Vec<(i32, i32, i32)> = Vec::new();
+        for (entity, x, y) in moved.iter() {
+            let entity = *entity;
+            let is_player = entity == *player_entity;
+            let idx = map.xy_idx(*x, *y);
+            let visible = map.visible_tiles[idx];
 
-                            hidden.remove(*entity_id); // The trap is no longer hidden
+            let tile_content = map.tile_content[idx].clone();
+            for entity_id in tile_content.iter() {
+                if entity == *entity_id { continue; } // Do not bother to check yourself for being a trap!
+                if entry_trigger.get(*entity_id).is_none() { continue; }
 
-                            // I the trap is damage inflicting, do it
-                            let damage = inflicts_damage.get(*entity_id);
-                            if let Some(damage) = damage {
-                                particle_builder.request(pos.x, pos.y, return_rgb(DMG_FG), return_rgb(DEFAULT_BG), rltk::to_cp437(POW_GLYPH), 200.0);
-                                SufferDamage::new_damage(&mut inflict_damage, entity, damage.damage);
-                            }
+                // We triggered it; only the player gets told about traps sprung out of sight
+                noise.make_noise(*x, *y, TRAP_NOISE);
+                if is_player || visible {
+                    if let Some(name) = names.get(*entity_id) {
+                        match (is_player, names.get(entity)) {
+                            (false, Some(victim)) => log.add(LogEntry::new(LogCategory::Danger).text("The ").name(&victim.name).text(" triggers a ").name(&name.name).text("!")),
+                            _ => log.push(LogCategory::Danger, format!("{} triggers!", &name.name))
+                        }
+                    }
+                    hidden.remove(*entity_id); // The trap is no longer hidden
+                }
+
+                // If the trap is damage inflicting, do it
+                if let Some(damage) = inflicts_damage.get(*entity_id) {
+                    particle_builder.request(*x, *y, return_rgb(DMG_FG), return_rgb(DEFAULT_BG), rltk::to_cp437(POW_GLYPH), 200.0);
+                    SufferDamage::new_damage(&mut inflict_damage, entity, damage.damage);
+                }
+
+                if let Some(poison) = inflicts_poison.get(*entity_id) {
+                    poisoned.insert(entity, Poisoned{ damage: poison.damage, turns: poison.turns }).expect("Unable to insert poison");
+                    if is_player { log.push(LogCategory::Danger, "You have been poisoned!"); }
+                }
+
+                if let Some(gas) = inflicts_confusion.get(*entity_id) {
+                    confusion.insert(entity, Confusion{ turns: gas.turns }).expect("Unable to insert confusion");
+                    if is_player { log.push(LogCategory::Danger, "The gas leaves you reeling!"); }
+                }
+
+                if let Some(alarm) = alarms.get(*entity_id) {
+                    alarms_raised.push((*x, *y, alarm.radius));
+                }
+
+                if teleports.get(*entity_id).is_some() {
+                    teleport_victims.push(entity);
+                }
 
-                            // If it is single activation, it needs to be removed
-                            let sa = single_activation.get(*entity_id);
-                            if let Some(_sa) = sa {
-                                remove_entities.push(*entity_id);
+                if pits.get(*entity_id).is_some() {
+                    if is_player {
+                        if map.depth < settings.final_depth {
+                            log.push(LogCategory::Danger, "The floor gives way and you fall to the level below!");
+                            *runstate = RunState::NextLevel;
+                        }
+                    } else {
+                        if visible {
+                            if let Some(victim) = names.get(entity) {
+                                log.push(LogCategory::General, format!("The {} falls out of sight.", &victim.name));
                             }
                         }
+                        map.blocked[idx] = false;
+                        entities.delete(entity).expect("Unable to delete pit victim");
                     }
                 }
+
+                // If it is single activation, it needs to be removed
+                if single_activation.get(*entity_id).is_some() {
+                    remove_entities.push(*entity_id);
+                }
             }
         }
 
@@ -64,7 +129,54 @@ impl<'a> System<'a> for TriggerSystem {
             entities.delete(*trap).expect("Unable to delete trap");
         }
 
+        // Alarms wake everything within earshot
+        for (x, y, radius) in alarms_raised.iter() {
+            log.push(LogCategory::Danger, "A loud alarm rings out!");
+            let mut woken: Vec<Entity> = Vec::new();
+            for (entity, _asleep, pos) in (&entities, &asleep, &position).join() {
+                let distance = rltk::DistanceAlg::Pythagoras.distance2d(Point::new(*x, *y), Point::new(pos.x, pos.y));
+                if distance <= *radius as f32 {
+                    woken.push(entity);
+                }
+            }
+            for entity in woken {
+                asleep.remove(entity);
+            }
+        }
+
+        // Teleport traps send their victim to a random open floor tile
+        for victim in teleport_victims {
+            let mut destination: Option<(i32, i32)> = None;
+            for _ in 0..1000 {
+                let x = rng.range(1, map.width - 1);
+                let y = rng.range(1, map.height - 1);
+                let idx = map.xy_idx(x, y);
+                if map.tiles[idx] == TileType::Floor && !map.blocked[idx] {
+                    destination = Some((x, y));
+                    break;
+                }
+            }
+
+            if let (Some((x, y)), Some(pos)) = (destination, position.get_mut(victim)) {
+                let old_idx = map.xy_idx(pos.x, pos.y);
+                map.blocked[old_idx] = false;
+                pos.x = x;
+                pos.y = y;
+                let new_idx = map.xy_idx(x, y);
+                map.blocked[new_idx] = true;
+
+                if let Some(viewshed) = viewsheds.get_mut(victim) {
+                    viewshed.dirty = true;
+                }
+                if victim == *player_entity {
+                    player_pos.x = x;
+                    player_pos.y = y;
+                    log.push(LogCategory::Danger, "The world twists around you!");
+                }
+            }
+        }
+
         // Remove all entity movement markers
         entity_moved.clear();
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,174 @@
+use rltk::RandomNumberGenerator;
+
+use super::{Map, TileType};
+
+/// A hand-authored room stamped into the map at milestone depths.
+///
+/// Template legend:
+/// `#` wall, `.` floor, `+` door, `L` locked door, `S` secret door, `!` guaranteed loot,
+/// `$` gold, `g` goblin, `o` orc, `W` Goblin Warlord, `O` Orc Overlord, `M` the McGuffin.
+/// The entrance door must be on the bottom row; a corridor is dug south from it.
+pub struct Vault {
+    pub template: &'static str
+}
+
+const TREASURE_ROOM: Vault = Vault {
+    template: "\
+#########
+#!.....!#
+#.#####.#
+#.#$!$#.#
+#.#...#.#
+#...g...#
+####L####"
+};
+
+const GOBLIN_BARRACKS: Vault = Vault {
+    template: "\
+###########
+#g...!...g#
+#.#######.#
+#.#$.!.$#.#
+#.##...##.#
+#....o....#
+#####S#####"
+};
+
+const WARLORD_LAIR: Vault = Vault {
+    template: "\
+#############
+#!.........!#
+#.###...###.#
+#.#.......#.#
+#...$.W.$...#
+#.#.......#.#
+#.###...###.#
+#g....!....g#
+######+######"
+};
+
+const OVERLORD_THRONE: Vault = Vault {
+    template: "\
+#############
+#$....!....$#
+#.###...###.#
+#.#...M...#.#
+#.....O.....#
+#.#.......#.#
+#.###...###.#
+#o....!....o#
+######+######"
+};
+
+/// Boss lairs guard depth 5 and the final level; every third level has a treasure vault.
+pub fn vault_for_depth(depth: i32, final_depth: i32) -> Option<&'static Vault> {
+    if depth == final_depth { return Some(&OVERLORD_THRONE); }
+    if depth == 5 { return Some(&WARLORD_LAIR); }
+    if depth > 0 && depth % 3 == 0 {
+        if (depth / 3) % 2 == 1 { return Some(&TREASURE_ROOM); }
+        return Some(&GOBLIN_BARRACKS);
+    }
+    None
+}
+
+/// Stamps the depth's vault (if any) into solid rock and digs a corridor from its
+/// entrance to the nearest room below it. Returns the spawn markers as (x, y, glyph).
+pub fn stamp_vault(map: &mut Map, final_depth: i32, rng: &mut RandomNumberGenerator) -> Vec<(i32, i32, char)> {
+    let mut spawns: Vec<(i32, i32, char)> = Vec::new();
+    let vault = match vault_for_depth(map.depth, final_depth) {
+        None => return spawns,
+        Some(vault) => vault
+    };
+
+    let lines: Vec<&str> = vault.template.lines().collect();
+    let height = lines.len() as i32;
+    let width = lines[0].chars().count() as i32;
+
+    for _ in 0..200 {
+        let x = rng.range(2, map.width - width - 2);
+        let y = rng.range(2, map.height - height - 2);
+
+        if !fits_in_rock(map, x, y, width, height) { continue; }
+
+        // The corridor runs south from the entrance, so there has to be a room below
+        let mut target: Option<(i32, i32)> = None;
+        let mut best_distance = i32::MAX;
+        for room in map.rooms.iter() {
+            let (room_x, room_y) = room.center();
+            if room_y <= y + height { continue; }
+            let distance = i32::abs(room_x - (x + width / 2)) + i32::abs(room_y - (y + height));
+            if distance < best_distance {
+                best_distance = distance;
+                target = Some((room_x, room_y));
+            }
+        }
+        let (room_x, room_y) = match target {
+            None => continue,
+            Some(target) => target
+        };
+
+        // Stamp the template
+        let mut entrance = (x + width / 2, y + height - 1);
+        for (ty, line) in lines.iter().enumerate() {
+            for (tx, glyph) in line.chars().enumerate() {
+                let (map_x, map_y) = (x + tx as i32, y + ty as i32);
+                let idx = map.xy_idx(map_x, map_y);
+                match glyph {
+                    '#' => map.tiles[idx] = TileType::Wall,
+                    '+' | 'L' | 'S' => {
+                        // A secret door stays wall until it is found
+                        map.tiles[idx] = if glyph == 'S' { TileType::Wall } else { TileType::Floor };
+                        entrance = (map_x, map_y);
+                        spawns.push((map_x, map_y, glyph));
+                    }
+                    '.' => map.tiles[idx] = TileType::Floor,
+                    _ => {
+                        map.tiles[idx] = TileType::Floor;
+                        spawns.push((map_x, map_y, glyph));
+                    }
+                }
+            }
+        }
+
+        // Dig south from the entrance, then across to the room
+        for corridor_y in entrance.1 + 1 ..= room_y {
+            let idx = map.xy_idx(entrance.0, corridor_y);
+            if map.tiles[idx] == TileType::Wall { map.tiles[idx] = TileType::Floor; }
+        }
+        for corridor_x in i32::min(entrance.0, room_x) ..= i32::max(entrance.0, room_x) {
+            let idx = map.xy_idx(corridor_x, room_y);
+            if map.tiles[idx] == TileType::Wall { map.tiles[idx] = TileType::Floor; }
+        }
+
+        // Walls were cracked before the vault went in; none of that may weaken the vault or
+        // linger on the floor that has replaced it
+        let map_width = map.width;
+        let tiles = &map.tiles;
+        map.wall_damage.retain(|idx, _| {
+            let (wall_x, wall_y) = (*idx as i32 % map_width, *idx as i32 / map_width);
+            let in_vault = wall_x >= x && wall_x < x + width && wall_y >= y && wall_y < y + height;
+            !in_vault && tiles[*idx] == TileType::Wall
+        });
+
+        return spawns;
+    }
+
+    spawns
+}
+
+/// The boss marker in the depth's vault, if it has one.
+pub fn boss_for_depth(depth: i32, final_depth: i32) -> Option<char> {
+    vault_for_depth(depth, final_depth).and_then(|vault| vault.template.chars().find(|glyph| *glyph == 'W' || *glyph == 'O'))
+}
+
+/// True if the rectangle and a one tile border around it are all wall.
+fn fits_in_rock(map: &Map, x: i32, y: i32, width: i32, height: i32) -> bool {
+    for check_y in y - 1 ..= y + height {
+        for check_x in x - 1 ..= x + width {
+            if check_x < 1 || check_x > map.width - 2 || check_y < 1 || check_y > map.height - 2 { return false; }
+            let idx = map.xy_idx(check_x, check_y);
+            if map.tiles[idx] != TileType::Wall { return false; }
+        }
+    }
+    true
+}
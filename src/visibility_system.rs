@@ -1,6 +1,7 @@
 use rltk::{field_of_view, Point};
 use specs::prelude::*;
-use super::{Hidden, game_log::GameLog, Map, Name, Player, Position, Viewshed};
+use super::{Door, Hidden, game_log::{GameLog, LogCategory}, Item, LastSeen, map::Remembered, Map, Monster, Name, Npc, ParticleLifetime, Player, Position,
+            Renderable, run_stats::RunStats, TileType, Viewshed};
 
 pub struct VisibilitySystem {}
 
@@ -13,12 +14,21 @@ impl<'a> System<'a> for VisibilitySystem {
                         WriteStorage<'a, Hidden>,
                         WriteExpect<'a, rltk::RandomNumberGenerator>,
                         WriteExpect<'a, GameLog>,
-                        ReadStorage<'a, Name>
+                        ReadStorage<'a, Name>,
+                        ReadStorage<'a, Door>,
+                        ReadStorage<'a, Item>,
+                        ReadStorage<'a, Renderable>,
+                        ReadStorage<'a, Monster>,
+                        ReadStorage<'a, Npc>,
+                        ReadStorage<'a, ParticleLifetime>,
+                        WriteStorage<'a, LastSeen>,
+                        ReadExpect<'a, RunStats>
                     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (mut map, entities, mut viewshed, pos, player,
-            mut hidden, mut rng, mut log, names) = data;
+            mut hidden, mut rng, mut log, names, doors, items, renderables, monsters, npcs, particles,
+            mut last_seen, run_stats) = data;
         
         for (ent, viewshed, pos) in (&entities, &mut viewshed, &pos).join() {
             viewshed.dirty = false;
@@ -29,11 +39,19 @@ impl<'a> System<'a> for VisibilitySystem {
             // If this is the player, reveal what they can see
             let p: Option<&Player> = player.get(ent);
             if let Some(_p) = p {
+                // Without light the player can only make out what is right next to them
+                let origin = Point::new(pos.x, pos.y);
+                viewshed.visible_tiles.retain(|tile| {
+                    map.is_lit(map.xy_idx(tile.x, tile.y)) || rltk::DistanceAlg::Pythagoras.distance2d(origin, *tile) < 1.5
+                });
+
                 for t in map.visible_tiles.iter_mut() { *t = false };
+                let mut found_doors: Vec<usize> = Vec::new();
                 for vis in viewshed.visible_tiles.iter() {
                     let idx = map.xy_idx(vis.x, vis.y);
                     map.revealed_tiles[idx] = true;
                     map.visible_tiles[idx] = true;
+                    map.remembered.remove(&idx);
 
                     // Chance to reveal hidden things
                     for e in map.tile_content[idx].iter() {
@@ -42,14 +60,46 @@ impl<'a> System<'a> for VisibilitySystem {
                             if rng.roll_dice(1, 24) == 1 {
                                 let name = names.get(*e);
                                 if let Some(name) = name {
-                                    log.entries.push(format!("You spotted a {}.", &name.name));
+                                    log.push(LogCategory::Danger, format!("You spotted a {}.", &name.name));
                                 }
                                 hidden.remove(*e);
+                                if doors.get(*e).is_some() { found_doors.push(idx); }
                             }
                         }
                     }
                 }
+
+                // A secret door stops looking like wall once it has been found
+                for idx in found_doors {
+                    map.tiles[idx] = TileType::Floor;
+                }
+            }
+        }
+
+        // Remember the topmost thing in view that stays put on each tile, and where each
+        // monster in view was spotted
+        let mut in_view = (&entities, &pos, &renderables, !&hidden).join()
+            .filter(|(_, pos, _, _)| map.visible_tiles[map.xy_idx(pos.x, pos.y)])
+            .collect::<Vec<_>>();
+        in_view.sort_by(|a, b| b.2.render_order.cmp(&a.2.render_order));
+        let mut spotted: Vec<Entity> = Vec::new();
+        for (ent, pos, render, _hidden) in in_view {
+            if monsters.get(ent).is_some() {
+                last_seen.insert(ent, LastSeen{ x: pos.x, y: pos.y, turn: run_stats.turns }).expect("Unable to insert sighting");
+                spotted.push(ent);
+            } else if player.get(ent).is_none() && npcs.get(ent).is_none() && particles.get(ent).is_none() {
+                let idx = map.xy_idx(pos.x, pos.y);
+                map.remembered.insert(idx, Remembered{ glyph: render.glyph, item: items.get(ent).is_some() });
             }
         }
+
+        // A monster isn't where it was last seen if the player can see that spot and it isn't there
+        let gone = (&entities, &last_seen).join()
+            .filter(|(ent, seen)| !spotted.contains(ent) && map.visible_tiles[map.xy_idx(seen.x, seen.y)])
+            .map(|(ent, _)| ent)
+            .collect::<Vec<_>>();
+        for ent in gone {
+            last_seen.remove(ent);
+        }
     }
 }
\ No newline at end of file